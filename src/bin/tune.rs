@@ -0,0 +1,180 @@
+//! Texel tuning of the evaluation parameters.
+//!
+//! Reads a file of positions, one per line as a FEN followed by the result of the game
+//! it was taken from (`1`, `0.5` or `0`, from White's point of view), and adjusts the
+//! parameters one step at a time for as long as that lowers the mean squared error
+//! between the predicted and actual results.
+//!
+//! Usage: `tune <positions> <output> [initial parameters]`
+
+use std::{fs, thread};
+
+use anyhow::{bail, Context};
+use chess_bot::{board::FastBoard, eval::EvalParams, piece::PieceColor};
+
+/// Upper limit for the scaling constant, which would otherwise grow without bound for
+/// data sets where the evaluation already predicts every result.
+const MAX_SCALING: f64 = 10.0;
+
+struct Position {
+    board: FastBoard,
+    result: f64,
+}
+
+fn main() -> anyhow::Result<()> {
+    let args: Vec<_> = std::env::args().skip(1).collect();
+    let (positions_path, output_path, initial_path) = match args.as_slice() {
+        [positions, output] => (positions, output, None),
+        [positions, output, initial] => (positions, output, Some(initial)),
+        _ => bail!("usage: tune <positions> <output> [initial parameters]"),
+    };
+
+    let mut params = match initial_path {
+        Some(path) => EvalParams::load(path)?,
+        None => EvalParams::default(),
+    };
+
+    let positions = load_positions(positions_path)?;
+    println!("Loaded {} positions", positions.len());
+
+    let scaling = optimal_scaling(&positions, &params);
+    println!("Scaling constant: {:.3}", scaling);
+
+    let parameter_count: usize = params
+        .fields_mut()
+        .iter()
+        .map(|(_, values)| values.len())
+        .sum();
+
+    let mut best_error = mean_error(&positions, &params, scaling);
+    println!("Initial error: {:.6}", best_error);
+
+    for iteration in 1.. {
+        let mut improved = false;
+
+        for index in 0..parameter_count {
+            for step in [1, -1] {
+                *parameter(&mut params, index) += step;
+
+                let error = mean_error(&positions, &params, scaling);
+                if error < best_error {
+                    best_error = error;
+                    improved = true;
+                    break;
+                }
+
+                *parameter(&mut params, index) -= step;
+            }
+        }
+
+        params.save(output_path)?;
+        println!("Iteration {}: error {:.6}", iteration, best_error);
+
+        if !improved {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+fn load_positions(path: &str) -> anyhow::Result<Vec<Position>> {
+    let text =
+        fs::read_to_string(path).with_context(|| format!("cannot read positions from {}", path))?;
+
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(line_number, line)| {
+            let Some((fen, result)) = line.trim().rsplit_once(' ') else {
+                bail!("missing result on line {}", line_number + 1);
+            };
+
+            let result = result
+                .parse()
+                .with_context(|| format!("invalid result on line {}", line_number + 1))?;
+            let board = FastBoard::from_fen(fen)
+                .with_context(|| format!("invalid FEN on line {}", line_number + 1))?;
+
+            Ok(Position { board, result })
+        })
+        .collect()
+}
+
+/// The `index`th parameter, counting through every field in order.
+fn parameter(params: &mut EvalParams, index: usize) -> &mut i32 {
+    params
+        .fields_mut()
+        .into_iter()
+        .flat_map(|(_, values)| values.iter_mut())
+        .nth(index)
+        .expect("parameter index out of range")
+}
+
+/// Predicted score of a position for White, between 0 and 1.
+fn win_probability(evaluation: i32, scaling: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-scaling * evaluation as f64 / 400.0))
+}
+
+fn mean_error(positions: &[Position], params: &EvalParams, scaling: f64) -> f64 {
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk_size = positions.len().div_ceil(threads).max(1);
+
+    let total: f64 = thread::scope(|scope| {
+        let handles: Vec<_> = positions
+            .chunks(chunk_size)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|position| {
+                            let evaluation = match position.board.next_to_move() {
+                                PieceColor::White => position.board.evaluate_with(params),
+                                PieceColor::Black => -position.board.evaluate_with(params),
+                            };
+                            (position.result - win_probability(evaluation, scaling)).powi(2)
+                        })
+                        .sum::<f64>()
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .sum()
+    });
+
+    total / positions.len() as f64
+}
+
+/// Finds the constant mapping evaluations to win probabilities which best fits the
+/// results for the initial parameters, so that tuning changes the evaluation rather
+/// than just its scale.
+fn optimal_scaling(positions: &[Position], params: &EvalParams) -> f64 {
+    let mut scaling = 1.0;
+    let mut step = 0.5;
+    let mut best_error = mean_error(positions, params, scaling);
+
+    while step > 0.001 {
+        let mut improved = false;
+        for candidate in [scaling + step, scaling - step] {
+            if !(0.0..=MAX_SCALING).contains(&candidate) {
+                continue;
+            }
+
+            let error = mean_error(positions, params, candidate);
+            if error < best_error {
+                best_error = error;
+                scaling = candidate;
+                improved = true;
+            }
+        }
+
+        if !improved {
+            step /= 2.0;
+        }
+    }
+
+    scaling
+}
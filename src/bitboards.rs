@@ -1,10 +1,65 @@
-use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, Not};
+use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Not};
+
+use lazy_static::lazy_static;
 
 use crate::{
     piece::{Piece, PieceColor, PieceType},
     position::BoardPosition,
 };
 
+/// The squares of each file, from the a-file to the h-file.
+pub const FILES: [Bitboard; 8] = {
+    let mut files = [Bitboard(0); 8];
+    let mut file = 0;
+    while file < 8 {
+        files[file] = Bitboard(0x0101010101010101 << file);
+        file += 1;
+    }
+    files
+};
+
+/// The squares of each rank, from the first rank to the eighth.
+pub const RANKS: [Bitboard; 8] = {
+    let mut ranks = [Bitboard(0); 8];
+    let mut rank = 0;
+    while rank < 8 {
+        ranks[rank] = Bitboard(0xff << (8 * rank));
+        rank += 1;
+    }
+    ranks
+};
+
+/// The squares of each diagonal running up to the right, indexed by `7 + rank - file`
+/// so that the a8 corner comes first and the h1 corner last.
+pub const DIAGONALS: [Bitboard; 15] = {
+    let mut diagonals = [Bitboard(0); 15];
+    let mut square = 0;
+    while square < 64 {
+        diagonals[7 + square / 8 - square % 8].0 |= 1 << square;
+        square += 1;
+    }
+    diagonals
+};
+
+/// The squares of each diagonal running up to the left, indexed by `rank + file` from
+/// the a1 corner to the h8 corner.
+pub const ANTI_DIAGONALS: [Bitboard; 15] = {
+    let mut diagonals = [Bitboard(0); 15];
+    let mut square = 0;
+    while square < 64 {
+        diagonals[square / 8 + square % 8].0 |= 1 << square;
+        square += 1;
+    }
+    diagonals
+};
+
+pub const LIGHT_SQUARES: Bitboard = Bitboard(0x55AA55AA55AA55AA);
+pub const DARK_SQUARES: Bitboard = Bitboard(!LIGHT_SQUARES.0);
+
+lazy_static! {
+    static ref LINES: Box<Lines> = Lines::generate();
+}
+
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
 pub struct Bitboard(u64);
 
@@ -45,6 +100,8 @@ impl Bitboard {
         self.0
     }
 
+    /// Moves every square by the given offset. Squares are not stopped at the edge of
+    /// the board, so a file offset wraps around onto the neighbouring rank.
     pub fn shift(self, ranks: i8, files: i8) -> Self {
         let shift = ranks * 8 + files;
         if shift >= 0 {
@@ -54,8 +111,54 @@ impl Bitboard {
         }
     }
 
-    pub fn filled_indices(&self) -> impl Iterator<Item = u8> + use<'_> {
-        (0..64).filter(|i| self.get(*i))
+    pub fn north(self) -> Self {
+        Self(self.0 << 8)
+    }
+
+    pub fn south(self) -> Self {
+        Self(self.0 >> 8)
+    }
+
+    pub fn east(self) -> Self {
+        Self((self.0 & !FILES[7].0) << 1)
+    }
+
+    pub fn west(self) -> Self {
+        Self((self.0 & !FILES[0].0) >> 1)
+    }
+
+    /// One rank towards the opponent of the given side.
+    pub fn forward(self, color: PieceColor) -> Self {
+        match color {
+            PieceColor::White => self.north(),
+            PieceColor::Black => self.south(),
+        }
+    }
+
+    /// The indices of the squares in the set, in ascending order.
+    pub fn filled_indices(&self) -> Squares {
+        self.into_iter()
+    }
+
+    /// The number of squares in the set.
+    pub fn count(&self) -> u32 {
+        self.0.count_ones()
+    }
+
+    /// The index of the lowest square in the set.
+    pub fn lsb(&self) -> Option<u8> {
+        self.any().then(|| self.0.trailing_zeros() as u8)
+    }
+
+    /// Removes the lowest set square and returns its index.
+    pub fn pop_lsb(&mut self) -> Option<u8> {
+        let index = self.lsb()?;
+        self.0 &= self.0 - 1;
+        Some(index)
+    }
+
+    pub fn more_than_one(&self) -> bool {
+        self.0 & self.0.wrapping_sub(1) != 0
     }
 
     pub fn is_empty(&self) -> bool {
@@ -81,6 +184,20 @@ impl BitOrAssign for Bitboard {
     }
 }
 
+impl BitXor for Bitboard {
+    type Output = Self;
+
+    fn bitxor(self, rhs: Self) -> Self::Output {
+        Self(self.0 ^ rhs.0)
+    }
+}
+
+impl BitXorAssign for Bitboard {
+    fn bitxor_assign(&mut self, rhs: Self) {
+        self.0 ^= rhs.0;
+    }
+}
+
 impl BitAnd for Bitboard {
     type Output = Self;
 
@@ -103,6 +220,101 @@ impl Not for Bitboard {
     }
 }
 
+impl IntoIterator for Bitboard {
+    type Item = u8;
+    type IntoIter = Squares;
+
+    fn into_iter(self) -> Squares {
+        Squares(self)
+    }
+}
+
+/// Iterates over the indices of the squares in a bitboard, lowest first.
+pub struct Squares(Bitboard);
+
+impl Iterator for Squares {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        self.0.pop_lsb()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let count = self.0.count() as usize;
+        (count, Some(count))
+    }
+}
+
+impl ExactSizeIterator for Squares {}
+
+/// The squares strictly between two squares on the same rank, file or diagonal, or none
+/// if they are not aligned.
+pub fn between(a: &BoardPosition, b: &BoardPosition) -> Bitboard {
+    LINES.between[a.index() as usize][b.index() as usize]
+}
+
+/// The whole rank, file or diagonal through two squares, from edge to edge, or none if
+/// they are not aligned.
+pub fn line(a: &BoardPosition, b: &BoardPosition) -> Bitboard {
+    LINES.line[a.index() as usize][b.index() as usize]
+}
+
+struct Lines {
+    between: [[Bitboard; 64]; 64],
+    line: [[Bitboard; 64]; 64],
+}
+
+impl Lines {
+    fn generate() -> Box<Self> {
+        let mut lines = Box::new(Self {
+            between: [[Bitboard::empty(); 64]; 64],
+            line: [[Bitboard::empty(); 64]; 64],
+        });
+
+        for a in 0..64u8 {
+            let from = BoardPosition::from_index(a);
+            let lines_through = [
+                RANKS[from.rank() as usize],
+                FILES[from.file() as usize],
+                DIAGONALS[(7 + from.rank() - from.file()) as usize],
+                ANTI_DIAGONALS[(from.rank() + from.file()) as usize],
+            ];
+
+            for (ranks, files) in [
+                (0, 1),
+                (1, 1),
+                (1, 0),
+                (1, -1),
+                (0, -1),
+                (-1, -1),
+                (-1, 0),
+                (-1, 1),
+            ] {
+                let mut squares = Bitboard::empty();
+                let mut rank = from.rank() as i8 + ranks;
+                let mut file = from.file() as i8 + files;
+
+                while (0..8).contains(&rank) && (0..8).contains(&file) {
+                    let b = (rank * 8 + file) as usize;
+                    let line = lines_through
+                        .into_iter()
+                        .find(|line| line.get(b as u8))
+                        .unwrap();
+
+                    lines.between[a as usize][b] = squares;
+                    lines.line[a as usize][b] = line;
+
+                    squares.set(b as u8, true);
+                    rank += ranks;
+                    file += files;
+                }
+            }
+        }
+
+        lines
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Bitboards {
     white: ColorBitboards,
@@ -143,19 +355,27 @@ impl Bitboards {
     }
 
     pub fn queens(&self) -> Bitboard {
-        self.white.queen & self.black.queen
+        self.white.queen | self.black.queen
     }
 
     pub fn kings(&self) -> Bitboard {
-        self.white.king & self.black.king
+        self.white.king | self.black.king
     }
 
     pub fn rooks(&self) -> Bitboard {
-        self.white.rook & self.black.rook
+        self.white.rook | self.black.rook
     }
 
     pub fn knights(&self) -> Bitboard {
-        self.white.knight & self.black.knight
+        self.white.knight | self.black.knight
+    }
+
+    pub fn bishops(&self) -> Bitboard {
+        self.white.bishop | self.black.bishop
+    }
+
+    pub fn pawns(&self) -> Bitboard {
+        self.white.pawn | self.black.pawn
     }
 }
 
@@ -1,11 +1,12 @@
 use std::collections::HashMap;
 
-use anyhow::bail;
-
-use crate::bitboards::{Bitboard, Bitboards};
+use crate::bitboards::{between, Bitboard, Bitboards, DARK_SQUARES, LIGHT_SQUARES, RANKS};
 use crate::castle::{CastlingRights, PlayerCastlingRights};
+use crate::eval::{self, EvalParams, MOBILITY_BASELINE};
 use crate::lookup::LOOKUP;
+use crate::movelist::MoveList;
 use crate::movement::{CastleDirection, Move, PastMove, Promotion};
+use crate::pawns::{PawnStructure, PawnTable};
 use crate::piece::{Piece, PieceColor, PieceType};
 use crate::position::BoardPosition;
 use crate::transposition::ZobristKey;
@@ -49,12 +50,48 @@ pub struct FastBoard {
     pub(crate) mailbox: Mailbox,
     pub(crate) move_list: Vec<PastMove>,
     pub(crate) zobrist_key: ZobristKey,
+    /// A Zobrist key of the pawns alone, for the pawn hash table.
+    pub(crate) pawn_key: ZobristKey,
     pub(crate) next_to_move: PieceColor,
     pub(crate) repetitions: HashMap<ZobristKey, u8>,
     bitboards: Bitboards,
     en_passant: Option<u8>,
     castling_rights: CastlingRights,
-    halfmoves: u64,
+    /// Plies since the last capture or pawn move, for the fifty-move rule.
+    halfmove_clock: u32,
+    /// The number of the current move, starting at 1 and incremented after Black moves.
+    fullmoves: u32,
+    eval: IncrementalEval,
+}
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum FenError {
+    #[error("FEN has {0} fields, expected 6")]
+    FieldCount(usize),
+    #[error("FEN has {0} ranks, expected 8")]
+    RankCount(usize),
+    #[error("Invalid piece `{0}`")]
+    InvalidPiece(char),
+    #[error("Rank {rank} does not have 8 squares")]
+    RankLength { rank: u8 },
+    #[error("Invalid side to move `{0}`")]
+    InvalidSideToMove(String),
+    #[error("Invalid castling rights `{0}`")]
+    InvalidCastlingRights(String),
+    #[error("Invalid en passant square `{0}`")]
+    InvalidEnPassant(String),
+    #[error("Invalid halfmove clock `{0}`")]
+    InvalidHalfmoveClock(String),
+    #[error("Invalid fullmove number `{0}`")]
+    InvalidFullmoveNumber(String),
+    #[error("{color:?} has {count} kings")]
+    KingCount { color: PieceColor, count: u32 },
+    #[error("Pawn on the first or last rank")]
+    PawnOnBackRank,
+    #[error("{0:?} has castling rights without its king and rook at home")]
+    CastlingWithoutPieces(PieceColor),
+    #[error("The side not to move is in check")]
+    OpponentInCheck,
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -84,79 +121,210 @@ impl FastBoard {
             next_to_move: PieceColor::White,
             en_passant: None,
             castling_rights: CastlingRights::default(),
-            zobrist_key: ZobristKey::new(),
-            halfmoves: 0,
+            zobrist_key: ZobristKey::with_castling_rights(&CastlingRights::default()),
+            pawn_key: ZobristKey::new(),
+            halfmove_clock: 0,
+            fullmoves: 1,
             repetitions: HashMap::new(),
+            eval: IncrementalEval::default(),
             // move_generator: LookupTables::new(),
         }
     }
 
-    pub fn from_fen(fen: &str) -> anyhow::Result<Self> {
-        let fields: Vec<_> = fen.split(" ").collect();
-        assert!(fields.len() == 6, "Incorrect number of FEN fields");
-        let ranks: Vec<_> = fields.get(0).unwrap().split("/").collect();
-        assert!(ranks.len() == 8, "Incorrect number of FEN ranks");
+    pub fn from_fen(fen: &str) -> Result<Self, FenError> {
+        let fields: Vec<_> = fen.split_whitespace().collect();
+        let &[placement, side, castling, en_passant, halfmove_clock, fullmoves] = fields.as_slice()
+        else {
+            return Err(FenError::FieldCount(fields.len()));
+        };
+
+        let ranks: Vec<_> = placement.split('/').collect();
+        if ranks.len() != 8 {
+            return Err(FenError::RankCount(ranks.len()));
+        }
 
         let mut board = Self::empty();
 
-        for (rank, rank_fen) in ranks.iter().enumerate() {
+        for (i, rank_fen) in ranks.iter().enumerate() {
+            let rank = 7 - i as u8;
             let mut file = 0;
+
             for char in rank_fen.chars() {
-                if let Some(digit) = char.to_digit(10) {
-                    file += digit;
-                } else {
-                    let piece = match char.to_ascii_lowercase() {
-                        'p' => PieceType::Pawn,
-                        'n' => PieceType::Knight,
-                        'b' => PieceType::Bishop,
-                        'r' => PieceType::Rook,
-                        'q' => PieceType::Queen,
-                        'k' => PieceType::King,
-                        _ => bail!("Invalid FEN piece"),
-                    };
-                    let color = match char.is_uppercase() {
-                        true => PieceColor::White,
-                        false => PieceColor::Black,
-                    };
+                if let Some(digit @ 1..=8) = char.to_digit(10) {
+                    file += digit as u8;
+                    if file > 8 {
+                        return Err(FenError::RankLength { rank: rank + 1 });
+                    }
+                    continue;
+                }
 
-                    board.place_piece(
-                        BoardPosition::from_rank_file(7 - rank as u8, file as u8),
-                        Piece::new(color, piece),
-                    );
+                let piece = match char.to_ascii_lowercase() {
+                    'p' => PieceType::Pawn,
+                    'n' => PieceType::Knight,
+                    'b' => PieceType::Bishop,
+                    'r' => PieceType::Rook,
+                    'q' => PieceType::Queen,
+                    'k' => PieceType::King,
+                    _ => return Err(FenError::InvalidPiece(char)),
+                };
+                let color = match char.is_uppercase() {
+                    true => PieceColor::White,
+                    false => PieceColor::Black,
+                };
 
-                    file += 1;
+                if file >= 8 {
+                    return Err(FenError::RankLength { rank: rank + 1 });
                 }
+
+                board.place_piece(
+                    BoardPosition::from_rank_file(rank, file),
+                    Piece::new(color, piece),
+                );
+
+                file += 1;
+            }
+
+            if file != 8 {
+                return Err(FenError::RankLength { rank: rank + 1 });
             }
         }
 
-        board.next_to_move = match fields[1] {
-            "w" => PieceColor::White,
-            "b" => PieceColor::Black,
-            _ => bail!("Invalid FEN next piece color"),
-        };
+        match side {
+            "w" => {}
+            "b" => board.toggle_next_player(),
+            _ => return Err(FenError::InvalidSideToMove(side.into())),
+        }
 
-        let rights = fields[2];
-        board.castling_rights = CastlingRights {
+        let rights = Self::parse_castling_rights(castling)
+            .ok_or_else(|| FenError::InvalidCastlingRights(castling.into()))?;
+        board
+            .zobrist_key
+            .toggle_castling_rights(&board.castling_rights);
+        board.zobrist_key.toggle_castling_rights(&rights);
+        board.castling_rights = rights;
+
+        if en_passant != "-" {
+            let file = board
+                .parse_en_passant(en_passant)
+                .ok_or_else(|| FenError::InvalidEnPassant(en_passant.into()))?;
+            board.set_en_passant(Some(file));
+        }
+
+        board.halfmove_clock = halfmove_clock
+            .parse()
+            .map_err(|_| FenError::InvalidHalfmoveClock(halfmove_clock.into()))?;
+        board.fullmoves = fullmoves
+            .parse()
+            .ok()
+            .filter(|&fullmoves| fullmoves >= 1)
+            .ok_or_else(|| FenError::InvalidFullmoveNumber(fullmoves.into()))?;
+
+        board.validate()?;
+
+        Ok(board)
+    }
+
+    fn parse_castling_rights(castling: &str) -> Option<CastlingRights> {
+        let mut rights = CastlingRights {
             white: PlayerCastlingRights {
-                queenside: rights.contains('Q'),
-                kingside: rights.contains('K'),
+                queenside: false,
+                kingside: false,
             },
             black: PlayerCastlingRights {
-                queenside: rights.contains('q'),
-                kingside: rights.contains('k'),
+                queenside: false,
+                kingside: false,
             },
         };
 
-        board.en_passant = if fields[3] != "-" {
-            let file_fen = fields[3].chars().next().unwrap();
-            Some((file_fen as u32 - 'a' as u32) as u8)
-        } else {
-            None
+        if castling == "-" {
+            return Some(rights);
+        }
+
+        for char in castling.chars() {
+            let right = match char {
+                'K' => &mut rights.white.kingside,
+                'Q' => &mut rights.white.queenside,
+                'k' => &mut rights.black.kingside,
+                'q' => &mut rights.black.queenside,
+                _ => return None,
+            };
+
+            // Each right may only be given once.
+            if *right {
+                return None;
+            }
+            *right = true;
+        }
+
+        Some(rights)
+    }
+
+    /// Parses the en passant target square, which must lie behind a pawn that could
+    /// just have moved two squares.
+    fn parse_en_passant(&self, square: &str) -> Option<u8> {
+        let &[file @ b'a'..=b'h', rank @ b'1'..=b'8'] = square.as_bytes() else {
+            return None;
         };
+        let target = BoardPosition::from_rank_file(rank - b'1', file - b'a');
 
-        board.halfmoves = fields[4].parse().unwrap();
+        if Some(target) != self.en_passant_target_on(target.file()) {
+            return None;
+        }
 
-        Ok(board)
+        let (pawn_rank, origin_rank) = match self.next_to_move {
+            PieceColor::White => (4, 6),
+            PieceColor::Black => (3, 1),
+        };
+        let pawn = BoardPosition::from_rank_file(pawn_rank, target.file());
+        let origin = BoardPosition::from_rank_file(origin_rank, target.file());
+
+        let pawn_moved =
+            self.mailbox.get(&pawn) == Some(Piece::new(self.next_to_move.other(), PieceType::Pawn));
+        let path_clear = self.mailbox.get(&target).is_none() && self.mailbox.get(&origin).is_none();
+
+        (pawn_moved && path_clear).then_some(target.file())
+    }
+
+    /// Checks that the position could occur in a game: one king each, no pawns on the
+    /// back ranks, castling rights only with the king and rook at home, and the side
+    /// which just moved not left in check.
+    fn validate(&mut self) -> Result<(), FenError> {
+        for color in [PieceColor::White, PieceColor::Black] {
+            let count = self.bitboards.get_color(color).king.count();
+            if count != 1 {
+                return Err(FenError::KingCount { color, count });
+            }
+        }
+
+        let back_ranks = Bitboard::from_value(0xff000000000000ff);
+        let pawns = self.bitboards.get_color(PieceColor::White).pawn
+            | self.bitboards.get_color(PieceColor::Black).pawn;
+        if (pawns & back_ranks).any() {
+            return Err(FenError::PawnOnBackRank);
+        }
+
+        for color in [PieceColor::White, PieceColor::Black] {
+            let rights = self.castling_rights.get(color);
+            let home_row = Self::home_row(color);
+            let at_home = |file, kind| {
+                self.mailbox
+                    .get(&BoardPosition::from_rank_file(home_row, file))
+                    == Some(Piece::new(color, kind))
+            };
+
+            let king_home = at_home(4, PieceType::King);
+            if (rights.kingside && !(king_home && at_home(7, PieceType::Rook)))
+                || (rights.queenside && !(king_home && at_home(0, PieceType::Rook)))
+            {
+                return Err(FenError::CastlingWithoutPieces(color));
+            }
+        }
+
+        if self.is_in_check(self.next_to_move.other()) {
+            return Err(FenError::OpponentInCheck);
+        }
+
+        Ok(())
     }
 
     pub fn initial() -> Self {
@@ -175,11 +343,19 @@ impl FastBoard {
         if let Some(replaced) = replaced {
             self.bitboards.remove(position.index(), replaced);
             self.zobrist_key.toggle_piece(&replaced, &position);
+            self.eval.remove(&position, &replaced, eval::params());
+            if replaced.kind == PieceType::Pawn {
+                self.pawn_key.toggle_piece(&replaced, &position);
+            }
         }
 
         // Insert the new piece into the bitboards
         self.bitboards.insert(position.index(), piece);
         self.zobrist_key.toggle_piece(&piece, &position);
+        self.eval.add(&position, &piece, eval::params());
+        if piece.kind == PieceType::Pawn {
+            self.pawn_key.toggle_piece(&piece, &position);
+        }
 
         replaced
     }
@@ -189,6 +365,10 @@ impl FastBoard {
         if let Some(piece) = piece {
             self.bitboards.remove(position.index(), piece);
             self.zobrist_key.toggle_piece(&piece, position);
+            self.eval.remove(position, &piece, eval::params());
+            if piece.kind == PieceType::Pawn {
+                self.pawn_key.toggle_piece(&piece, position);
+            }
             // print_bitboard(self.bitboards.get_color(piece.color));
         }
 
@@ -334,6 +514,23 @@ impl FastBoard {
         previous_en_passant
     }
 
+    /// The square a pawn moving next may capture en passant onto, if any.
+    pub fn en_passant_target(&self) -> Option<BoardPosition> {
+        self.en_passant
+            .and_then(|file| self.en_passant_target_on(file))
+    }
+
+    /// The square a pawn on the given file would have passed over, if it had just moved
+    /// two squares.
+    fn en_passant_target_on(&self, file: u8) -> Option<BoardPosition> {
+        let rank = match self.next_to_move {
+            PieceColor::White => 5,
+            PieceColor::Black => 2,
+        };
+
+        Some(BoardPosition::from_rank_file(rank, file))
+    }
+
     fn toggle_next_player(&mut self) {
         self.next_to_move = self.next_to_move.other();
         self.zobrist_key.toggle_player();
@@ -361,7 +558,7 @@ impl FastBoard {
                 }
 
                 let captured = self.mailbox.get(&to);
-                let captured_kind = captured.map(|p| p.kind);
+                let mut captured_kind = captured.map(|p| p.kind);
                 if let Some(captured_piece) = captured {
                     if captured_piece.color == self.next_to_move {
                         return Err(MoveError::CapturesOwnPiece {
@@ -371,17 +568,35 @@ impl FastBoard {
                     }
                 }
 
+                let en_passant_capture =
+                    moved_piece.kind == PieceType::Pawn && Some(to) == self.en_passant_target();
+
                 let previous_en_passant = self.update_en_passant(&moved_piece, &from, &to);
 
                 let previous_castle = self.update_castling_rights(&from, &to);
 
+                // The pawn taken en passant is beside the destination, not on it.
+                if en_passant_capture {
+                    self.remove_piece(&(from.rank(), to.file()).into());
+                    captured_kind = Some(PieceType::Pawn);
+                }
+
                 let past_move = PastMove::new(
                     board_move,
                     captured_kind,
+                    en_passant_capture,
                     previous_castle,
                     previous_en_passant,
+                    self.halfmove_clock,
                 );
 
+                // Captures and pawn moves are irreversible, so restart the clock.
+                if moved_piece.kind == PieceType::Pawn || captured_kind.is_some() {
+                    self.halfmove_clock = 0;
+                } else {
+                    self.halfmove_clock += 1;
+                }
+
                 // Move the piece
                 let mut piece = self.remove_piece(&from).unwrap();
                 // If the piece has been promoted, change its type.
@@ -394,29 +609,18 @@ impl FastBoard {
             }
 
             Move::Castle(direction) => {
-                if self.is_in_check(self.next_to_move) {
-                    return Err(MoveError::IllegalMove);
-                }
-
-                let rank = Self::home_row(self.next_to_move);
-                let mid_files = match direction {
-                    CastleDirection::QueenSide => [1, 2, 3].iter(),
-                    CastleDirection::KingSide => [5, 6].iter(),
+                // The king may not castle out of, through or into check. The knight's
+                // square on the queenside only needs to be empty.
+                let king_path = match direction {
+                    CastleDirection::QueenSide => 2..=4,
+                    CastleDirection::KingSide => 4..=6,
                 };
+                let home_row = Self::home_row(self.next_to_move);
 
-                if let Err(MoveError::IllegalMove) = mid_files
-                    .map(|file| {
-                        let mid_move = Move::Direct {
-                            from: BoardPosition::from_rank_file(rank, 4),
-                            to: BoardPosition::from_rank_file(rank, *file),
-                            promotion: None,
-                        };
-                        self.make_move(mid_move)?;
-                        self.unmake_last_move();
-                        Ok(())
-                    })
-                    .collect::<Result<Vec<()>, MoveError>>()
-                {
+                if king_path.into_iter().any(|file| {
+                    let square = BoardPosition::from_rank_file(home_row, file);
+                    self.is_square_attacked(&square, self.next_to_move.other())
+                }) {
                     return Err(MoveError::IllegalMove);
                 }
 
@@ -427,9 +631,12 @@ impl FastBoard {
                 let past_move = PastMove::new(
                     board_move,
                     None,
+                    false,
                     Some(self.castling_rights),
                     self.en_passant,
+                    self.halfmove_clock,
                 );
+                self.halfmove_clock += 1;
 
                 self.set_en_passant(None);
 
@@ -449,10 +656,13 @@ impl FastBoard {
 
         self.move_list.push(past_move);
         self.toggle_next_player();
-        self.halfmoves += 1;
+        if self.next_to_move == PieceColor::White {
+            self.fullmoves += 1;
+        }
 
         if self.is_in_check(self.next_to_move.other()) {
-            self.unmake_last_move();
+            // The position was never counted, so don't touch the repetitions.
+            self.revert_last_move();
             return Err(MoveError::IllegalMove);
         }
 
@@ -465,15 +675,23 @@ impl FastBoard {
     }
 
     pub fn unmake_last_move(&mut self) {
-        self.halfmoves -= 1;
         self.repetitions
             .entry(self.zobrist_key)
             .and_modify(|r| *r -= 1);
 
+        self.revert_last_move();
+    }
+
+    fn revert_last_move(&mut self) {
         // dbg!(&self.move_list);
         let previous_move = self.move_list.pop().unwrap();
         // println!("UNMAKING MOVE");
 
+        self.halfmove_clock = previous_move.previous_halfmove_clock;
+        if self.next_to_move == PieceColor::White {
+            self.fullmoves -= 1;
+        }
+
         if let Some(previous_rights) = previous_move.previous_castling_rights {
             self.zobrist_key
                 .toggle_castling_rights(&self.castling_rights);
@@ -498,7 +716,11 @@ impl FastBoard {
                 self.place_piece(from, piece);
 
                 if let Some(captured) = previous_move.captured {
-                    self.place_piece(to, Piece::new(self.next_to_move, captured));
+                    let captured_position = match previous_move.en_passant_capture {
+                        true => (from.rank(), to.file()).into(),
+                        false => to,
+                    };
+                    self.place_piece(captured_position, Piece::new(self.next_to_move, captured));
                 }
             }
             Move::Castle(direction) => {
@@ -510,14 +732,9 @@ impl FastBoard {
         self.toggle_next_player();
     }
 
-    pub fn calculate_pseudo_moves(&self) -> Vec<Move> {
-        let mut moves = Vec::with_capacity(212);
-        for (pos, piece) in self.mailbox.iter() {
-            if piece.color == self.next_to_move {
-                let mut piece_moves = self.calculate_pseudo_moves_for_piece(&pos, &piece);
-                moves.append(&mut piece_moves);
-            }
-        }
+    pub fn calculate_pseudo_moves(&self) -> MoveList {
+        let mut moves = MoveList::new();
+        self.push_piece_moves(&mut moves, |_, _| Bitboard::ones());
 
         let castle_options = self.castling_rights.get(self.next_to_move);
         let (kingside_mask, queenside_mask) = match self.next_to_move {
@@ -539,36 +756,346 @@ impl FastBoard {
         moves
     }
 
-    pub fn calculate_pseudo_moves_for_piece<'a>(
+    /// The strictly legal moves of the side to move. Checkers, pinned pieces and the
+    /// squares the opponent attacks are found up front, so that no move has to be made
+    /// to find out whether it leaves the king in check.
+    pub fn legal_moves(&self) -> MoveList {
+        let us = self.next_to_move;
+        let them = us.other();
+        let occupied = self.bitboards.all_pieces();
+        let king = self.king_square(us);
+
+        let mut moves = MoveList::new();
+
+        // The king must not step along the line of a checking slider either, so the
+        // attacked squares are found as if it were not on the board.
+        let danger = self.attacked_squares(them, occupied & !Bitboard::from_pos(&king));
+        self.push_moves_onto(&mut moves, &king, PieceType::King, !danger);
+
+        // Any other move must capture the checker or block its line.
+        let checkers = self.attackers_to(&king, occupied) & self.bitboards.get_color(them).any();
+        // Only the king can escape a double check.
+        if checkers.more_than_one() {
+            return moves;
+        }
+        let check_mask = match checkers.lsb() {
+            Some(checker) => checkers | between(&king, &BoardPosition::from_index(checker)),
+            None => Bitboard::ones(),
+        };
+
+        let pin_rays = self.pin_rays(&king);
+        let en_passant = match self.en_passant_target() {
+            Some(target) => Bitboard::from_pos(&target),
+            None => Bitboard::empty(),
+        };
+
+        self.push_piece_moves(&mut moves, |kind, from| {
+            let targets = check_mask & pin_rays[from as usize];
+            match kind {
+                PieceType::King => Bitboard::empty(),
+                PieceType::Pawn => targets & !en_passant,
+                _ => targets,
+            }
+        });
+
+        self.push_en_passant_moves(&mut moves, &king);
+
+        if checkers.is_empty() {
+            let rights = self.castling_rights.get(us);
+            let home_row = |mask: u64| Bitboard::from_value(mask << (8 * Self::home_row(us)));
+
+            // The king may not pass through an attacked square. The knight's square on
+            // the queenside only needs to be empty.
+            if rights.kingside
+                && (occupied & home_row(0x60)).is_empty()
+                && (danger & home_row(0x60)).is_empty()
+            {
+                moves.push(Move::Castle(CastleDirection::KingSide));
+            }
+            if rights.queenside
+                && (occupied & home_row(0x0e)).is_empty()
+                && (danger & home_row(0x0c)).is_empty()
+            {
+                moves.push(Move::Castle(CastleDirection::QueenSide));
+            }
+        }
+
+        moves
+    }
+
+    /// En passant captures which do not expose the king. Both pawns leave their
+    /// squares, which can uncover an attack along the rank as well as a diagonal, so
+    /// the attacks on the king are recomputed with the pawns moved.
+    fn push_en_passant_moves(&self, moves: &mut MoveList, king: &BoardPosition) {
+        let Some(target) = self.en_passant_target() else {
+            return;
+        };
+
+        let us = self.next_to_move;
+        let captured = Bitboard::from_pos(&target).forward(us.other());
+        let mut capturers =
+            LOOKUP.pawn_attacks(&target, us.other()) & self.bitboards.get_color(us).pawn;
+
+        while let Some(from) = capturers.pop_lsb() {
+            let from = BoardPosition::from_index(from);
+            let occupied = (self.bitboards.all_pieces() & !Bitboard::from_pos(&from) & !captured)
+                | Bitboard::from_pos(&target);
+
+            let enemies = self.bitboards.get_color(us.other()).any() & !captured;
+            if (self.attackers_to(king, occupied) & enemies).is_empty() {
+                moves.push(Move::Direct {
+                    from,
+                    to: target,
+                    promotion: None,
+                });
+            }
+        }
+    }
+
+    /// The line each piece of the side to move is confined to by a pin against its
+    /// king, including the pinning piece. Unpinned pieces may move anywhere.
+    fn pin_rays(&self, king: &BoardPosition) -> [Bitboard; 64] {
+        let us = self.next_to_move;
+        let occupied = self.bitboards.all_pieces();
+        let own_pieces = self.bitboards.get_color(us).any();
+        let enemies = self.bitboards.get_color(us.other());
+
+        // Enemy sliders which would attack the king if none of our pieces were in the way.
+        let mut snipers = (LOOKUP.rook_lookup(king, enemies.any())
+            & (enemies.rook | enemies.queen))
+            | (LOOKUP.bishop_lookup(king, enemies.any()) & (enemies.bishop | enemies.queen));
+
+        let mut pin_rays = [Bitboard::ones(); 64];
+        while let Some(sniper) = snipers.pop_lsb() {
+            let sniper = BoardPosition::from_index(sniper);
+            let line = between(king, &sniper);
+            let blockers = line & occupied;
+
+            if let Some(pinned) = (blockers & own_pieces).lsb() {
+                if !blockers.more_than_one() {
+                    pin_rays[pinned as usize] = line | Bitboard::from_pos(&sniper);
+                }
+            }
+        }
+
+        pin_rays
+    }
+
+    /// The pieces of both colours attacking a square, with sliding pieces blocked by
+    /// `occupancy`. Passing an occupancy other than the board's own shows what would
+    /// attack the square once pieces have moved, e.g. with an exchanged piece removed.
+    pub fn attackers_to(&self, square: &BoardPosition, occupancy: Bitboard) -> Bitboard {
+        let pieces = &self.bitboards;
+        let white_pawns = pieces.get_color(PieceColor::White).pawn;
+        let black_pawns = pieces.get_color(PieceColor::Black).pawn;
+
+        (LOOKUP.rook_lookup(square, occupancy) & (pieces.rooks() | pieces.queens()))
+            | (LOOKUP.bishop_lookup(square, occupancy) & (pieces.bishops() | pieces.queens()))
+            | (LOOKUP.knight_lookup(square) & pieces.knights())
+            | (LOOKUP.king_lookup(square) & pieces.kings())
+            | (LOOKUP.pawn_attacks(square, PieceColor::Black) & white_pawns)
+            | (LOOKUP.pawn_attacks(square, PieceColor::White) & black_pawns)
+    }
+
+    /// Whether any piece of the given colour attacks a square.
+    pub fn is_square_attacked(&self, square: &BoardPosition, by: PieceColor) -> bool {
+        let attackers = self.attackers_to(square, self.bitboards.all_pieces());
+        (attackers & self.bitboards.get_color(by).any()).any()
+    }
+
+    /// Static exchange evaluation: the material won by a capture once both sides have
+    /// traded off every piece attacking its square, least valuable first, each side
+    /// stopping as soon as going on would lose material. Pins are ignored, and quiet
+    /// moves and castling are worth nothing.
+    pub fn static_exchange(&self, board_move: Move) -> i32 {
+        let Move::Direct {
+            from,
+            to,
+            promotion,
+        } = board_move
+        else {
+            return 0;
+        };
+        let Some(attacker) = self.mailbox.get(&from) else {
+            return 0;
+        };
+
+        let piece_values = &eval::params().piece_values;
+        let value = |kind| match kind {
+            // The king has no material value, but can never be given up in an exchange.
+            PieceType::King => KING_EXCHANGE_VALUE,
+            kind => piece_values[eval::piece_index(kind)],
+        };
+
+        let mut occupancy = self.bitboards.all_pieces() ^ Bitboard::from_pos(&from);
+        let captured = match self.mailbox.get(&to) {
+            Some(piece) => value(piece.kind),
+            None if attacker.kind == PieceType::Pawn && from.file() != to.file() => {
+                let captured_pawn = BoardPosition::from_rank_file(from.rank(), to.file());
+                occupancy ^= Bitboard::from_pos(&captured_pawn);
+                value(PieceType::Pawn)
+            }
+            None => 0,
+        };
+        let (mut on_square, promoted) = match promotion {
+            Some(promotion) => {
+                let kind = promotion.piece_type();
+                (kind, value(kind) - value(PieceType::Pawn))
+            }
+            None => (attacker.kind, 0),
+        };
+
+        // The material each capture wins for the side making it if the exchange stopped
+        // there. Every capture takes a piece off the board, so there are at most 32.
+        let mut gains = [0; 32];
+        gains[0] = captured + promoted;
+        let mut depth = 0;
+        let mut side = attacker.color.other();
+
+        loop {
+            let pieces = self.bitboards.get_color(side);
+            let attackers = self.attackers_to(&to, occupancy) & occupancy;
+            let least_valuable = [
+                (PieceType::Pawn, pieces.pawn),
+                (PieceType::Knight, pieces.knight),
+                (PieceType::Bishop, pieces.bishop),
+                (PieceType::Rook, pieces.rook),
+                (PieceType::Queen, pieces.queen),
+                (PieceType::King, pieces.king),
+            ]
+            .into_iter()
+            .find_map(|(kind, pieces)| Some((kind, (pieces & attackers).lsb()?)));
+
+            let Some((kind, index)) = least_valuable else {
+                break;
+            };
+
+            depth += 1;
+            gains[depth] = value(on_square) - gains[depth - 1];
+            occupancy ^= Bitboard::from_index(index);
+            on_square = kind;
+            side = side.other();
+        }
+
+        // Walk back through the exchange, letting each side decline a recapture which
+        // would leave it worse off than stopping.
+        while depth > 0 {
+            gains[depth - 1] = -(-gains[depth - 1]).max(gains[depth]);
+            depth -= 1;
+        }
+
+        gains[0]
+    }
+
+    /// Every square attacked by the given colour, with sliding pieces blocked by
+    /// `occupied`.
+    fn attacked_squares(&self, by: PieceColor, occupied: Bitboard) -> Bitboard {
+        let pieces = self.bitboards.get_color(by);
+        let mut attacked = pawn_attacks(by, pieces.pawn);
+
+        let mut knights = pieces.knight;
+        while let Some(index) = knights.pop_lsb() {
+            attacked |= LOOKUP.knight_lookup(&BoardPosition::from_index(index));
+        }
+        let mut kings = pieces.king;
+        while let Some(index) = kings.pop_lsb() {
+            attacked |= LOOKUP.king_lookup(&BoardPosition::from_index(index));
+        }
+        let mut rooks = pieces.rook | pieces.queen;
+        while let Some(index) = rooks.pop_lsb() {
+            attacked |= LOOKUP.rook_lookup(&BoardPosition::from_index(index), occupied);
+        }
+        let mut bishops = pieces.bishop | pieces.queen;
+        while let Some(index) = bishops.pop_lsb() {
+            attacked |= LOOKUP.bishop_lookup(&BoardPosition::from_index(index), occupied);
+        }
+
+        attacked
+    }
+
+    fn king_square(&self, color: PieceColor) -> BoardPosition {
+        let king = self.bitboards.get_color(color).king;
+        BoardPosition::from_index(king.lsb().expect("every position has a king"))
+    }
+
+    /// Pseudo-legal captures (including en passant) and promotions, for use in the
+    /// quiescence search.
+    pub fn calculate_pseudo_captures(&self) -> MoveList {
+        let enemy_mask = self.bitboards.get_color(self.next_to_move.other()).any();
+        let promotion_rank = RANKS[Self::home_row(self.next_to_move.other()) as usize];
+
+        let pawn_targets = match self.en_passant_target() {
+            Some(target) => enemy_mask | promotion_rank | Bitboard::from_pos(&target),
+            None => enemy_mask | promotion_rank,
+        };
+
+        let mut moves = MoveList::new();
+        self.push_piece_moves(&mut moves, |kind, _| match kind {
+            PieceType::Pawn => pawn_targets,
+            _ => enemy_mask,
+        });
+
+        moves
+    }
+
+    /// Adds the pseudo-legal moves of every piece of the side to move, restricted to
+    /// the targets given for the piece's kind and square.
+    fn push_piece_moves(&self, moves: &mut MoveList, targets: impl Fn(PieceType, u8) -> Bitboard) {
+        let pieces = self.bitboards.get_color(self.next_to_move);
+
+        for (kind, bitboard) in [
+            (PieceType::Pawn, pieces.pawn),
+            (PieceType::Knight, pieces.knight),
+            (PieceType::Bishop, pieces.bishop),
+            (PieceType::Rook, pieces.rook),
+            (PieceType::Queen, pieces.queen),
+            (PieceType::King, pieces.king),
+        ] {
+            let mut bitboard = bitboard;
+            while let Some(index) = bitboard.pop_lsb() {
+                let targets = targets(kind, index);
+                if targets.any() {
+                    let from = BoardPosition::from_index(index);
+                    self.push_moves_onto(moves, &from, kind, targets);
+                }
+            }
+        }
+    }
+
+    /// Adds the pseudo-legal moves for a piece whose destination is one of the `targets`.
+    fn push_moves_onto(
         &self,
-        position: &'a BoardPosition,
-        piece: &'a Piece,
-    ) -> Vec<Move> {
+        moves: &mut MoveList,
+        position: &BoardPosition,
+        kind: PieceType,
+        targets: Bitboard,
+    ) {
         let piece_mask = self.bitboards.all_pieces();
         let enemy_mask = self.bitboards.get_color(self.next_to_move.other()).any();
         let own_piece_mask = self.bitboards.get_color(self.next_to_move).any();
 
-        let mut legal_square_bitboard = match piece.kind {
+        let mut destinations = match kind {
             PieceType::King => LOOKUP.king_lookup(position),
             PieceType::Queen => LOOKUP.queen_lookup(position, piece_mask),
             PieceType::Bishop => LOOKUP.bishop_lookup(position, piece_mask),
             PieceType::Knight => LOOKUP.knight_lookup(position),
             PieceType::Rook => LOOKUP.rook_lookup(position, piece_mask),
             PieceType::Pawn => {
-                LOOKUP.pawn_lookup(position, self.next_to_move, piece_mask, enemy_mask)
+                // Pawns may also capture onto the square skipped by a double step.
+                let capture_mask = match self.en_passant_target() {
+                    Some(target) => enemy_mask | Bitboard::from_pos(&target),
+                    None => enemy_mask,
+                };
+                LOOKUP.pawn_lookup(position, self.next_to_move, piece_mask, capture_mask)
             }
         };
 
-        // print_bitboard(legal_square_bitboard);
-        legal_square_bitboard &= !own_piece_mask;
-        // print_bitboard(legal_square_bitboard);
+        destinations &= targets & !own_piece_mask;
 
-        let mut moves = Vec::with_capacity(16);
-        for index in legal_square_bitboard.filled_indices() {
+        let promotion_row = Self::home_row(self.next_to_move.other());
+        while let Some(index) = destinations.pop_lsb() {
             let dest = BoardPosition::from_index(index);
-            if piece.kind == PieceType::Pawn
-                && dest.rank() == Self::home_row(self.next_to_move.other())
-            {
+            if kind == PieceType::Pawn && dest.rank() == promotion_row {
                 for promotion in [
                     Promotion::Rook,
                     Promotion::Queen,
@@ -589,73 +1116,294 @@ impl FastBoard {
                 });
             }
         }
+    }
 
-        moves
+    /// Counts the leaf nodes of the legal move tree to the given depth, for verifying
+    /// move generation against published results.
+    pub fn perft(&mut self, depth: u32) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+
+        let legal_moves = self.legal_moves();
+        if depth == 1 {
+            return legal_moves.len() as u64;
+        }
+
+        let mut nodes = 0;
+        for possible_move in legal_moves {
+            self.make_move(possible_move).unwrap();
+            nodes += self.perft(depth - 1);
+            self.unmake_last_move();
+        }
+
+        nodes
     }
 
-    #[must_use]
-    pub fn is_in_check(&mut self, color: PieceColor) -> bool {
-        let king_pos = self
-            .mailbox
-            .iter()
-            .filter(|(_, piece)| piece.kind == PieceType::King && piece.color == color)
-            .map(|(pos, _)| pos)
-            .next()
-            .unwrap();
+    /// Splits the perft count at the given depth by the legal moves of this position.
+    pub fn divide(&mut self, depth: u32) -> Vec<(Move, u64)> {
+        let mut counts = Vec::new();
+        if depth == 0 {
+            return counts;
+        }
+
+        for possible_move in self.legal_moves() {
+            self.make_move(possible_move).unwrap();
+            counts.push((possible_move, self.perft(depth - 1)));
+            self.unmake_last_move();
+        }
 
-        let mask = self.bitboards.all_pieces();
-        let bitboards = self.bitboards.get_color(color.other());
+        counts
+    }
 
-        #[rustfmt::skip]
-        return (LOOKUP.queen_lookup (&king_pos, mask)  & bitboards.queen).any()
-            || (LOOKUP.rook_lookup  (&king_pos, mask)  & bitboards.rook).any()
-            || (LOOKUP.bishop_lookup(&king_pos, mask)  & bitboards.bishop).any()
-            || (LOOKUP.king_lookup  (&king_pos)        & bitboards.king).any()
-            || (LOOKUP.knight_lookup(&king_pos)        & bitboards.knight).any()
-            || (LOOKUP.pawn_attacks (&king_pos, color) & bitboards.pawn).any();
+    #[must_use]
+    pub fn is_in_check(&self, color: PieceColor) -> bool {
+        self.is_square_attacked(&self.king_square(color), color.other())
     }
 
     pub fn evaluate(&self) -> i32 {
+        let params = eval::params();
+        self.evaluate_terms(
+            self.incremental_eval(),
+            &self.pawn_structure(params),
+            params,
+        )
+    }
+
+    /// Evaluates as [`FastBoard::evaluate`], looking up the pawn structure in a cache.
+    pub fn evaluate_cached(&self, pawn_table: &mut PawnTable) -> i32 {
+        let params = eval::params();
+        let pawns = pawn_table.get_or_insert(self.pawn_key, || self.pawn_structure(params));
+        self.evaluate_terms(self.incremental_eval(), &pawns, params)
+    }
+
+    /// Evaluates from scratch with the given parameters rather than the engine's own,
+    /// e.g. while tuning them.
+    pub fn evaluate_with(&self, params: &EvalParams) -> i32 {
+        let totals = IncrementalEval::compute(&self.mailbox, params);
+        self.evaluate_terms(&totals, &self.pawn_structure(params), params)
+    }
+
+    fn incremental_eval(&self) -> &IncrementalEval {
+        debug_assert_eq!(
+            self.eval,
+            IncrementalEval::compute(&self.mailbox, eval::params()),
+            "incremental evaluation out of sync with the board"
+        );
+
+        &self.eval
+    }
+
+    fn pawn_structure(&self, params: &EvalParams) -> PawnStructure {
+        PawnStructure::evaluate(
+            self.bitboards.get_color(PieceColor::White).pawn,
+            self.bitboards.get_color(PieceColor::Black).pawn,
+            params,
+        )
+    }
+
+    fn evaluate_terms(
+        &self,
+        totals: &IncrementalEval,
+        pawns: &PawnStructure,
+        params: &EvalParams,
+    ) -> i32 {
         // A positive score favours whoever is next to move.
         // A negative score favours whoever just moved.
-        let mut score = 0;
-
-        for (position, piece) in self.mailbox.iter() {
-            let piece_value = match piece.kind {
-                PieceType::King => 20000,
-                PieceType::Queen => 900,
-                PieceType::Rook => 500,
-                PieceType::Bishop => 330,
-                PieceType::Knight => 320,
-                PieceType::Pawn => 100,
-            };
+        let us = self.next_to_move.index();
+        let them = self.next_to_move.other().index();
+        let mut midgame_score = totals.midgame[us] - totals.midgame[them];
+        let mut endgame_score = totals.endgame[us] - totals.endgame[them];
+
+        let occupied = self.bitboards.all_pieces();
+        let (white_passed_midgame, white_passed_endgame) =
+            pawns.passed_pawn_bonus(PieceColor::White, occupied, params);
+        let (black_passed_midgame, black_passed_endgame) =
+            pawns.passed_pawn_bonus(PieceColor::Black, occupied, params);
+
+        let (white_activity_midgame, white_activity_endgame) =
+            self.activity(PieceColor::White, params);
+        let (black_activity_midgame, black_activity_endgame) =
+            self.activity(PieceColor::Black, params);
+
+        // Positional terms are scored from White's point of view.
+        let positional_midgame = pawns.midgame + white_passed_midgame - black_passed_midgame
+            + white_activity_midgame
+            - black_activity_midgame;
+        let positional_endgame = pawns.endgame + white_passed_endgame - black_passed_endgame
+            + white_activity_endgame
+            - black_activity_endgame;
+
+        match self.next_to_move {
+            PieceColor::White => {
+                midgame_score += positional_midgame;
+                endgame_score += positional_endgame;
+            }
+            PieceColor::Black => {
+                midgame_score -= positional_midgame;
+                endgame_score -= positional_endgame;
+            }
+        }
 
-            let piece_square_table = match piece.kind {
-                PieceType::King => KING_MIDGAME_PST,
-                PieceType::Queen => QUEEN_PST,
-                PieceType::Bishop => BISHOP_PST,
-                PieceType::Knight => KNIGHT_PST,
-                PieceType::Rook => ROOK_PST,
-                PieceType::Pawn => PAWN_PST,
-            };
+        // Blend the two scores according to how much material has been traded off.
+        let phase = totals.phase.min(MAX_PHASE);
+        (midgame_score * phase + endgame_score * (MAX_PHASE - phase)) / MAX_PHASE
+    }
 
-            let pst_index = match piece.color {
-                PieceColor::Black => position.rank * 8 + position.file,
-                PieceColor::White => (7 - position.rank) * 8 + position.file,
-            };
+    /// Mobility and king safety scores for one side, as (midgame, endgame).
+    fn activity(&self, color: PieceColor, params: &EvalParams) -> (i32, i32) {
+        let own = self.bitboards.get_color(color);
+        let enemy = self.bitboards.get_color(color.other());
+        let occupied = self.bitboards.all_pieces();
+
+        // Squares attacked by enemy pawns are not counted towards mobility, as moving
+        // there would usually lose the piece.
+        let safe_squares = !own.any() & !pawn_attacks(color.other(), enemy.pawn);
 
-            let square_value = piece_square_table[pst_index as usize];
+        let enemy_king_zone = match enemy.king.lsb() {
+            Some(king) => {
+                let king = BoardPosition::from_index(king);
+                LOOKUP.king_lookup(&king) | Bitboard::from_pos(&king)
+            }
+            None => Bitboard::empty(),
+        };
 
-            let total_value = piece_value + square_value;
+        let mut midgame = 0;
+        let mut endgame = 0;
+        let mut king_attackers = 0;
+        let mut king_attack_weight = 0;
+
+        let pieces = [own.knight, own.bishop, own.rook, own.queen];
+        let kinds = [
+            PieceType::Knight,
+            PieceType::Bishop,
+            PieceType::Rook,
+            PieceType::Queen,
+        ];
+
+        for (i, (kind, pieces)) in kinds.into_iter().zip(pieces).enumerate() {
+            for square in pieces.filled_indices() {
+                let square = BoardPosition::from_index(square);
+                let attacks = match kind {
+                    PieceType::Knight => LOOKUP.knight_lookup(&square),
+                    PieceType::Bishop => LOOKUP.bishop_lookup(&square, occupied),
+                    PieceType::Rook => LOOKUP.rook_lookup(&square, occupied),
+                    _ => LOOKUP.queen_lookup(&square, occupied),
+                };
 
-            if piece.color == self.next_to_move {
-                score += total_value;
-            } else {
-                score -= total_value;
+                let moves = (attacks & safe_squares).count() as i32;
+                midgame += (moves - MOBILITY_BASELINE[i]) * params.mobility_midgame[i];
+                endgame += (moves - MOBILITY_BASELINE[i]) * params.mobility_endgame[i];
+
+                let zone_attacks = (attacks & enemy_king_zone).count() as i32;
+                if zone_attacks > 0 {
+                    king_attackers += 1;
+                    king_attack_weight += params.king_attack_weight[i] * zone_attacks;
+                }
             }
         }
 
-        score
+        // A lone attacker is rarely dangerous, but the danger grows quickly as more
+        // pieces join in.
+        if king_attackers >= 2 {
+            midgame +=
+                (king_attack_weight * king_attack_weight / 8).min(params.max_king_attack_bonus);
+        }
+
+        midgame -= self.pawn_shield_penalty(color) * params.pawn_shield_penalty;
+
+        (midgame, endgame)
+    }
+
+    /// The number of pawns missing in front of a king which is still on its back ranks.
+    fn pawn_shield_penalty(&self, color: PieceColor) -> i32 {
+        let own = self.bitboards.get_color(color);
+        let Some(king) = own.king.lsb() else {
+            return 0;
+        };
+
+        let king = BoardPosition::from_index(king);
+        let (relative_rank, forward) = match color {
+            PieceColor::White => (king.rank(), 1),
+            PieceColor::Black => (7 - king.rank(), -1),
+        };
+
+        if relative_rank > 1 {
+            return 0;
+        }
+
+        let shield_ranks = [1, 2].map(|distance| (king.rank() as i8 + forward * distance) as u8);
+        let files = king.file().saturating_sub(1)..=(king.file() + 1).min(7);
+
+        let missing = files
+            .filter(|&file| {
+                !shield_ranks.iter().any(|&rank| {
+                    own.pawn
+                        .get(BoardPosition::from_rank_file(rank, file).index())
+                })
+            })
+            .count() as i32;
+
+        missing
+    }
+
+    pub fn castling_rights(&self) -> CastlingRights {
+        self.castling_rights
+    }
+
+    pub fn bitboards(&self) -> &Bitboards {
+        &self.bitboards
+    }
+
+    pub fn zobrist_key(&self) -> ZobristKey {
+        self.zobrist_key
+    }
+
+    pub fn next_to_move(&self) -> PieceColor {
+        self.next_to_move
+    }
+
+    pub fn halfmove_clock(&self) -> u32 {
+        self.halfmove_clock
+    }
+
+    pub fn fullmoves(&self) -> u32 {
+        self.fullmoves
+    }
+
+    /// Whether a hundred plies have passed without a capture or pawn move. A checkmate
+    /// delivered on the last of them still stands, so check that first.
+    pub fn is_fifty_move_draw(&self) -> bool {
+        self.halfmove_clock >= 100
+    }
+
+    /// How far the game is from the endgame, from [`MAX_PHASE`] with all the minor and
+    /// major pieces on the board down to zero with none of them left.
+    pub fn game_phase(&self) -> i32 {
+        // Promotions can leave more material than the starting position.
+        self.eval.phase.min(MAX_PHASE)
+    }
+
+    /// Whether neither side has enough material left to ever deliver checkmate: bare
+    /// kings, a single minor piece, or only bishops which all stand on one colour.
+    pub fn has_insufficient_material(&self) -> bool {
+        let white = self.bitboards.get_color(PieceColor::White);
+        let black = self.bitboards.get_color(PieceColor::Black);
+
+        let mating_material = white.pawn | white.rook | white.queen;
+        if (mating_material | black.pawn | black.rook | black.queen).any() {
+            return false;
+        }
+
+        let knights = self.bitboards.knights();
+        let bishops = self.bitboards.bishops();
+
+        if !(knights | bishops).more_than_one() {
+            return true;
+        }
+
+        knights.is_empty()
+            && ((bishops & LIGHT_SQUARES).is_empty() || (bishops & DARK_SQUARES).is_empty())
     }
 
     pub fn check_board_state(&self, fen: &str) -> bool {
@@ -720,105 +1468,99 @@ impl FastBoard {
             castling.push('q');
         }
 
+        if castling.is_empty() {
+            castling.push('-');
+        }
+
         // EN PASSANT
-        let en_passant = match self.en_passant {
-            Some(ep) => ep.to_string(),
+        let en_passant = match self.en_passant_target() {
+            Some(target) => target.to_string(),
             None => "-".into(),
         };
 
-        // COUNTER
-        let full_moves = self.halfmoves / 2;
-
         format!(
             "{} {} {} {} {} {}",
-            board, to_move, castling, en_passant, self.halfmoves, full_moves,
+            board, to_move, castling, en_passant, self.halfmove_clock, self.fullmoves,
         )
     }
 }
 
-#[rustfmt::skip]
-const PAWN_PST: [i32; 64] = [
-     0,   0,   0,   0,   0,   0,   0,   0,
-    50,  50,  50,  50,  50,  50,  50,  50,
-    10,  10,  20,  30,  30,  20,  10,  10,
-     5,   5,  10,  25,  25,  10,   5,   5,
-     0,   0,   0,  20,  20,   0,   0,   0,
-     5,  -5, -10,   0,   0, -10,  -5,   5,
-     5,  10,  10, -20, -20,  10,  10,   5,
-     0,   0,   0,   0,   0,   0,   0,   0,
-];
-
-#[rustfmt::skip]
-const KNIGHT_PST: [i32; 64] = [
-    -50, -40, -30, -30, -30, -30, -40, -50,
-    -40, -20,   0,   0,   0,   0, -20, -40,
-    -30,   0,  10,  15,  15,  10,   0, -30,
-    -30,   5,  15,  20,  20,  15,   5, -30,
-    -30,   0,  15,  20,  20,  15,   0, -30,
-    -30,   5,  10,  15,  15,  10,   5, -30,
-    -40, -20,   0,   5,   5,   0, -20, -40,
-    -50, -40, -30, -30, -30, -30, -40, -50,
-];
-
-#[rustfmt::skip]
-const BISHOP_PST: [i32; 64] = [
-    -20, -10, -10, -10, -10, -10, -10, -20,
-    -10,   0,   0,   0,   0,   0,   0, -10,
-    -10,   0,   5,  10,  10,   5,   0, -10,
-    -10,   5,   5,  10,  10,   5,   5, -10,
-    -10,   0,  10,  10,  10,  10,   0, -10,
-    -10,  10,  10,  10,  10,  10,  10, -10,
-    -10,   5,   0,   0,   0,   0,   5, -10,
-    -20, -10, -10, -10, -10, -10, -10, -20,
-];
-
-#[rustfmt::skip]
-const ROOK_PST: [i32; 64] = [
-     0,   0,   0,   0,   0,   0,   0,   0,
-     5,  10,  10,  10,  10,  10,  10,   5,
-    -5,   0,   0,   0,   0,   0,   0,  -5,
-    -5,   0,   0,   0,   0,   0,   0,  -5,
-    -5,   0,   0,   0,   0,   0,   0,  -5,
-    -5,   0,   0,   0,   0,   0,   0,  -5,
-    -5,   0,   0,   0,   0,   0,   0,  -5,
-     0,   0,   0,   5,   5,   0,   0,   0,
-];
-
-#[rustfmt::skip]
-const QUEEN_PST: [i32; 64] = [
-    -20, -10, -10,  -5,  -5, -10, -10, -20,
-    -10,   0,   0,   0,   0,   0,   0, -10,
-    -10,   0,   5,   5,   5,   5,   0, -10,
-     -5,   0,   5,   5,   5,   5,   0,  -5,
-      0,   0,   5,   5,   5,   5,   0,  -5,
-    -10,   5,   5,   5,   5,   5,   0, -10,
-    -10,   0,   5,   0,   0,   0,   0, -10,
-    -20, -10, -10,  -5,  -5, -10, -10, -20,
-];
-
-#[rustfmt::skip]
-const KING_MIDGAME_PST: [i32; 64] = [
-    -30, -40, -40, -50, -50, -40, -40, -30,
-    -30, -40, -40, -50, -50, -40, -40, -30,
-    -30, -40, -40, -50, -50, -40, -40, -30,
-    -30, -40, -40, -50, -50, -40, -40, -30,
-    -20, -30, -30, -40, -40, -30, -30, -20,
-    -10, -20, -20, -20, -20, -20, -20, -10,
-     20,  20,   0,   0,   0,   0,  20,  20,
-     20,  30,  10,   0,   0,  10,  30,  20,
-];
-
-#[rustfmt::skip]
-const KING_ENDGAME_PST: [i32; 64] = [
-    -50, -40, -30, -20, -20, -30, -40, -50,
-    -30, -20, -10,   0,   0, -10, -20, -30,
-    -30, -10,  20,  30,  30,  20, -10, -30,
-    -30, -10,  30,  40,  40,  30, -10, -30,
-    -30, -10,  30,  40,  40,  30, -10, -30,
-    -30, -10,  20,  30,  30,  20, -10, -30,
-    -30, -30,   0,   0,   0,   0, -30, -30,
-    -50, -30, -30, -30, -30, -30, -30, -50,
-];
+/// Squares attacked by the given pawns.
+fn pawn_attacks(color: PieceColor, pawns: Bitboard) -> Bitboard {
+    let forward = pawns.forward(color);
+    forward.east() | forward.west()
+}
+
+/// Material, piece-square and game phase totals, kept up to date as pieces are placed
+/// and removed so that evaluation does not need to scan the board.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct IncrementalEval {
+    /// Scores for each colour, indexed white then black.
+    midgame: [i32; 2],
+    endgame: [i32; 2],
+    phase: i32,
+}
+
+impl IncrementalEval {
+    /// Computes the totals from scratch.
+    fn compute(mailbox: &Mailbox, params: &EvalParams) -> Self {
+        let mut eval = Self::default();
+        for (position, piece) in mailbox.iter() {
+            eval.add(&position, &piece, params);
+        }
+
+        eval
+    }
+
+    fn add(&mut self, position: &BoardPosition, piece: &Piece, params: &EvalParams) {
+        let (midgame, endgame) = Self::piece_values(position, piece, params);
+        self.midgame[piece.color.index()] += midgame;
+        self.endgame[piece.color.index()] += endgame;
+        self.phase += Self::phase_weight(piece.kind);
+    }
+
+    fn remove(&mut self, position: &BoardPosition, piece: &Piece, params: &EvalParams) {
+        let (midgame, endgame) = Self::piece_values(position, piece, params);
+        self.midgame[piece.color.index()] -= midgame;
+        self.endgame[piece.color.index()] -= endgame;
+        self.phase -= Self::phase_weight(piece.kind);
+    }
+
+    /// The midgame and endgame values of a piece on a square.
+    fn piece_values(position: &BoardPosition, piece: &Piece, params: &EvalParams) -> (i32, i32) {
+        let index = eval::piece_index(piece.kind);
+        let midgame_table = &params.midgame_pst[index];
+        let endgame_table = &params.endgame_pst[index];
+
+        // The tables are written from White's side, with the eighth rank first.
+        let pst_index = match piece.color {
+            PieceColor::White => (7 - position.rank) * 8 + position.file,
+            PieceColor::Black => position.rank * 8 + position.file,
+        };
+
+        let piece_value = params.piece_values[index];
+
+        (
+            piece_value + midgame_table[pst_index as usize],
+            piece_value + endgame_table[pst_index as usize],
+        )
+    }
+
+    fn phase_weight(kind: PieceType) -> i32 {
+        match kind {
+            PieceType::Knight | PieceType::Bishop => 1,
+            PieceType::Rook => 2,
+            PieceType::Queen => 4,
+            PieceType::Pawn | PieceType::King => 0,
+        }
+    }
+}
+
+/// The game phase with all the minor and major pieces still on the board.
+pub const MAX_PHASE: i32 = 24;
+
+/// What losing the king counts for in an exchange: more than all the other pieces.
+const KING_EXCHANGE_VALUE: i32 = 20_000;
 
 // mod tests {
 //     use crate::{board::FastBoard, print_board};
@@ -0,0 +1,351 @@
+use std::{fs, path::Path, sync::OnceLock};
+
+use anyhow::{anyhow, bail, Context};
+
+use crate::piece::PieceType;
+
+static PARAMS: OnceLock<EvalParams> = OnceLock::new();
+
+/// Typical numbers of safe squares attacked by knights, bishops, rooks and queens.
+/// Mobility is scored relative to these, so that it does not shift material values.
+pub const MOBILITY_BASELINE: [i32; 4] = [4, 6, 7, 13];
+
+/// The evaluation parameters used by the engine: the defaults, unless others were set
+/// with [`set_params`] before the first evaluation.
+pub fn params() -> &'static EvalParams {
+    PARAMS.get_or_init(EvalParams::default)
+}
+
+/// Sets the evaluation parameters used by the engine. This must happen before any
+/// board is created, as boards keep running totals computed with the parameters.
+pub fn set_params(params: EvalParams) -> anyhow::Result<()> {
+    PARAMS
+        .set(params)
+        .map_err(|_| anyhow!("evaluation parameters are already in use"))
+}
+
+/// Index of a piece type in the per-piece parameter tables.
+pub fn piece_index(kind: PieceType) -> usize {
+    match kind {
+        PieceType::Pawn => 0,
+        PieceType::Knight => 1,
+        PieceType::Bishop => 2,
+        PieceType::Rook => 3,
+        PieceType::Queen => 4,
+        PieceType::King => 5,
+    }
+}
+
+const PIECE_NAMES: [&str; 6] = ["pawn", "knight", "bishop", "rook", "queen", "king"];
+
+/// Weights of every evaluation term. Paired weights are (midgame, endgame), and
+/// piece-square tables are laid out from White's point of view, starting at a8.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EvalParams {
+    /// Material values, indexed by [`piece_index`].
+    pub piece_values: [i32; 6],
+    pub midgame_pst: [[i32; 64]; 6],
+    pub endgame_pst: [[i32; 64]; 6],
+    pub doubled_penalty: [i32; 2],
+    pub isolated_penalty: [i32; 2],
+    pub backward_penalty: [i32; 2],
+    /// Passed pawn bonuses by rank, counted from the pawn's own side of the board.
+    pub passed_midgame_bonus: [i32; 8],
+    pub passed_endgame_bonus: [i32; 8],
+    /// Per-square mobility weights for knights, bishops, rooks and queens.
+    pub mobility_midgame: [i32; 4],
+    pub mobility_endgame: [i32; 4],
+    /// Danger of each knight, bishop, rook and queen attack on the enemy king's zone.
+    pub king_attack_weight: [i32; 4],
+    pub max_king_attack_bonus: i32,
+    pub pawn_shield_penalty: i32,
+}
+
+impl Default for EvalParams {
+    fn default() -> Self {
+        Self {
+            piece_values: [100, 320, 330, 500, 900, 0],
+            midgame_pst: [
+                PAWN_MIDGAME_PST,
+                KNIGHT_MIDGAME_PST,
+                BISHOP_MIDGAME_PST,
+                ROOK_MIDGAME_PST,
+                QUEEN_MIDGAME_PST,
+                KING_MIDGAME_PST,
+            ],
+            endgame_pst: [
+                PAWN_ENDGAME_PST,
+                KNIGHT_ENDGAME_PST,
+                BISHOP_ENDGAME_PST,
+                ROOK_ENDGAME_PST,
+                QUEEN_ENDGAME_PST,
+                KING_ENDGAME_PST,
+            ],
+            doubled_penalty: [10, 20],
+            isolated_penalty: [10, 15],
+            backward_penalty: [8, 10],
+            passed_midgame_bonus: [0, 5, 10, 15, 25, 40, 60, 0],
+            passed_endgame_bonus: [0, 10, 20, 35, 55, 85, 120, 0],
+            mobility_midgame: [4, 5, 2, 1],
+            mobility_endgame: [4, 5, 4, 2],
+            king_attack_weight: [2, 2, 3, 5],
+            max_king_attack_bonus: 500,
+            pawn_shield_penalty: 15,
+        }
+    }
+}
+
+impl EvalParams {
+    /// Every parameter with its name, in the order they are saved.
+    pub fn fields_mut(&mut self) -> Vec<(String, &mut [i32])> {
+        let mut fields: Vec<(String, &mut [i32])> =
+            vec![("piece_values".into(), &mut self.piece_values)];
+
+        for (name, table) in PIECE_NAMES.iter().zip(&mut self.midgame_pst) {
+            fields.push((format!("{}_midgame_pst", name), table));
+        }
+        for (name, table) in PIECE_NAMES.iter().zip(&mut self.endgame_pst) {
+            fields.push((format!("{}_endgame_pst", name), table));
+        }
+
+        fields.extend([
+            ("doubled_penalty".into(), &mut self.doubled_penalty[..]),
+            ("isolated_penalty".into(), &mut self.isolated_penalty),
+            ("backward_penalty".into(), &mut self.backward_penalty),
+            (
+                "passed_midgame_bonus".into(),
+                &mut self.passed_midgame_bonus,
+            ),
+            (
+                "passed_endgame_bonus".into(),
+                &mut self.passed_endgame_bonus,
+            ),
+            ("mobility_midgame".into(), &mut self.mobility_midgame),
+            ("mobility_endgame".into(), &mut self.mobility_endgame),
+            ("king_attack_weight".into(), &mut self.king_attack_weight),
+            (
+                "max_king_attack_bonus".into(),
+                std::slice::from_mut(&mut self.max_king_attack_bonus),
+            ),
+            (
+                "pawn_shield_penalty".into(),
+                std::slice::from_mut(&mut self.pawn_shield_penalty),
+            ),
+        ]);
+
+        fields
+    }
+
+    /// Reads parameters saved by [`EvalParams::save`]. Any parameters missing from the
+    /// file keep their default values.
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("cannot read parameters from {}", path.display()))?;
+
+        Self::from_text(&text)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        fs::write(path, self.to_text())
+            .with_context(|| format!("cannot write parameters to {}", path.display()))
+    }
+
+    /// Parses parameters from lines of `<name> <values...>`. Blank lines and lines
+    /// starting with `#` are ignored.
+    pub fn from_text(text: &str) -> anyhow::Result<Self> {
+        let mut params = Self::default();
+        let mut fields = params.fields_mut();
+
+        for (line_number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut words = line.split_whitespace();
+            let name = words.next().unwrap();
+
+            let Some((_, field)) = fields.iter_mut().find(|(field, _)| field == name) else {
+                bail!("unknown parameter `{}` on line {}", name, line_number + 1);
+            };
+
+            let values = words
+                .map(|word| word.parse())
+                .collect::<Result<Vec<i32>, _>>()
+                .with_context(|| format!("invalid value on line {}", line_number + 1))?;
+
+            if values.len() != field.len() {
+                bail!(
+                    "expected {} values for `{}`, found {}",
+                    field.len(),
+                    name,
+                    values.len()
+                );
+            }
+
+            field.copy_from_slice(&values);
+        }
+
+        drop(fields);
+        Ok(params)
+    }
+
+    pub fn to_text(&self) -> String {
+        // Fields are only listed mutably, so format a copy.
+        let mut params = self.clone();
+        let mut text = String::new();
+
+        for (name, values) in params.fields_mut() {
+            let values: Vec<_> = values.iter().map(|value| value.to_string()).collect();
+            text.push_str(&format!("{} {}\n", name, values.join(" ")));
+        }
+
+        text
+    }
+}
+
+#[rustfmt::skip]
+const PAWN_MIDGAME_PST: [i32; 64] = [
+     0,   0,   0,   0,   0,   0,   0,   0,
+    50,  50,  50,  50,  50,  50,  50,  50,
+    10,  10,  20,  30,  30,  20,  10,  10,
+     5,   5,  10,  25,  25,  10,   5,   5,
+     0,   0,   0,  20,  20,   0,   0,   0,
+     5,  -5, -10,   0,   0, -10,  -5,   5,
+     5,  10,  10, -20, -20,  10,  10,   5,
+     0,   0,   0,   0,   0,   0,   0,   0,
+];
+
+#[rustfmt::skip]
+const PAWN_ENDGAME_PST: [i32; 64] = [
+     0,   0,   0,   0,   0,   0,   0,   0,
+    80,  80,  80,  80,  80,  80,  80,  80,
+    50,  50,  50,  50,  50,  50,  50,  50,
+    30,  30,  30,  30,  30,  30,  30,  30,
+    15,  15,  15,  15,  15,  15,  15,  15,
+     5,   5,   5,   5,   5,   5,   5,   5,
+     0,   0,   0,   0,   0,   0,   0,   0,
+     0,   0,   0,   0,   0,   0,   0,   0,
+];
+
+#[rustfmt::skip]
+const KNIGHT_MIDGAME_PST: [i32; 64] = [
+    -50, -40, -30, -30, -30, -30, -40, -50,
+    -40, -20,   0,   0,   0,   0, -20, -40,
+    -30,   0,  10,  15,  15,  10,   0, -30,
+    -30,   5,  15,  20,  20,  15,   5, -30,
+    -30,   0,  15,  20,  20,  15,   0, -30,
+    -30,   5,  10,  15,  15,  10,   5, -30,
+    -40, -20,   0,   5,   5,   0, -20, -40,
+    -50, -40, -30, -30, -30, -30, -40, -50,
+];
+
+#[rustfmt::skip]
+const KNIGHT_ENDGAME_PST: [i32; 64] = [
+    -50, -40, -30, -30, -30, -30, -40, -50,
+    -40, -20, -10,  -5,  -5, -10, -20, -40,
+    -30, -10,  10,  15,  15,  10, -10, -30,
+    -30,  -5,  15,  20,  20,  15,  -5, -30,
+    -30,  -5,  15,  20,  20,  15,  -5, -30,
+    -30, -10,  10,  15,  15,  10, -10, -30,
+    -40, -20, -10,  -5,  -5, -10, -20, -40,
+    -50, -40, -30, -30, -30, -30, -40, -50,
+];
+
+#[rustfmt::skip]
+const BISHOP_MIDGAME_PST: [i32; 64] = [
+    -20, -10, -10, -10, -10, -10, -10, -20,
+    -10,   0,   0,   0,   0,   0,   0, -10,
+    -10,   0,   5,  10,  10,   5,   0, -10,
+    -10,   5,   5,  10,  10,   5,   5, -10,
+    -10,   0,  10,  10,  10,  10,   0, -10,
+    -10,  10,  10,  10,  10,  10,  10, -10,
+    -10,   5,   0,   0,   0,   0,   5, -10,
+    -20, -10, -10, -10, -10, -10, -10, -20,
+];
+
+#[rustfmt::skip]
+const BISHOP_ENDGAME_PST: [i32; 64] = [
+    -20, -10, -10, -10, -10, -10, -10, -20,
+    -10,   0,   0,   0,   0,   0,   0, -10,
+    -10,   0,  10,  10,  10,  10,   0, -10,
+    -10,   0,  10,  15,  15,  10,   0, -10,
+    -10,   0,  10,  15,  15,  10,   0, -10,
+    -10,   0,  10,  10,  10,  10,   0, -10,
+    -10,   0,   0,   0,   0,   0,   0, -10,
+    -20, -10, -10, -10, -10, -10, -10, -20,
+];
+
+#[rustfmt::skip]
+const ROOK_MIDGAME_PST: [i32; 64] = [
+     0,   0,   0,   0,   0,   0,   0,   0,
+     5,  10,  10,  10,  10,  10,  10,   5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+     0,   0,   0,   5,   5,   0,   0,   0,
+];
+
+#[rustfmt::skip]
+const ROOK_ENDGAME_PST: [i32; 64] = [
+     5,   5,   5,   5,   5,   5,   5,   5,
+    10,  10,  10,  10,  10,  10,  10,  10,
+     0,   0,   0,   0,   0,   0,   0,   0,
+     0,   0,   0,   0,   0,   0,   0,   0,
+     0,   0,   0,   0,   0,   0,   0,   0,
+     0,   0,   0,   0,   0,   0,   0,   0,
+     0,   0,   0,   0,   0,   0,   0,   0,
+     0,   0,   0,   0,   0,   0,   0,   0,
+];
+
+#[rustfmt::skip]
+const QUEEN_MIDGAME_PST: [i32; 64] = [
+    -20, -10, -10,  -5,  -5, -10, -10, -20,
+    -10,   0,   0,   0,   0,   0,   0, -10,
+    -10,   0,   5,   5,   5,   5,   0, -10,
+     -5,   0,   5,   5,   5,   5,   0,  -5,
+      0,   0,   5,   5,   5,   5,   0,  -5,
+    -10,   5,   5,   5,   5,   5,   0, -10,
+    -10,   0,   5,   0,   0,   0,   0, -10,
+    -20, -10, -10,  -5,  -5, -10, -10, -20,
+];
+
+#[rustfmt::skip]
+const QUEEN_ENDGAME_PST: [i32; 64] = [
+    -20, -10, -10,  -5,  -5, -10, -10, -20,
+    -10,   0,   5,   5,   5,   5,   0, -10,
+    -10,   5,  10,  10,  10,  10,   5, -10,
+     -5,   5,  10,  15,  15,  10,   5,  -5,
+     -5,   5,  10,  15,  15,  10,   5,  -5,
+    -10,   5,  10,  10,  10,  10,   5, -10,
+    -10,   0,   5,   5,   5,   5,   0, -10,
+    -20, -10, -10,  -5,  -5, -10, -10, -20,
+];
+
+#[rustfmt::skip]
+const KING_MIDGAME_PST: [i32; 64] = [
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -20, -30, -30, -40, -40, -30, -30, -20,
+    -10, -20, -20, -20, -20, -20, -20, -10,
+     20,  20,   0,   0,   0,   0,  20,  20,
+     20,  30,  10,   0,   0,  10,  30,  20,
+];
+
+#[rustfmt::skip]
+const KING_ENDGAME_PST: [i32; 64] = [
+    -50, -40, -30, -20, -20, -30, -40, -50,
+    -30, -20, -10,   0,   0, -10, -20, -30,
+    -30, -10,  20,  30,  30,  20, -10, -30,
+    -30, -10,  30,  40,  40,  30, -10, -30,
+    -30, -10,  30,  40,  40,  30, -10, -30,
+    -30, -10,  20,  30,  30,  20, -10, -30,
+    -30, -30,   0,   0,   0,   0, -30, -30,
+    -50, -30, -30, -30, -30, -30, -30, -50,
+];
@@ -1,11 +1,18 @@
 pub mod bitboards;
 pub mod board;
 pub mod castle;
+pub mod eval;
 pub mod lookup;
 pub mod magics;
 pub mod masks;
+pub mod movelist;
 pub mod movement;
+pub mod ordering;
+pub mod pawns;
+pub mod pgn;
 pub mod piece;
 pub mod position;
+pub mod san;
 pub mod search;
 pub mod transposition;
+pub mod uci;
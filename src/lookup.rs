@@ -23,6 +23,12 @@ pub struct LookupTables {
     bishop_lookup: SlidingPieceLookup<7296>,
 }
 
+impl Default for LookupTables {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl LookupTables {
     pub fn new() -> Self {
         Self {
@@ -108,18 +114,22 @@ impl PawnLookup {
         let c_rank = Bitboard::from_value(0xff0000);
         let f_rank = Bitboard::from_value(0xff0000000000);
 
+        // Pieces directly in front of a pawn block its double step, but a pawn which
+        // has already stepped forward must not block its own next move.
+        let blockers = piece_mask & !Bitboard::from_pos(square);
+
         match color {
             PieceColor::White => {
                 board |= self.white_move_masks[square.index() as usize]
                     & !piece_mask
-                    & !(piece_mask & c_rank).shift(1, 0);
+                    & !(blockers & c_rank).shift(1, 0);
                 board |= self.white_attack_masks[square.index() as usize] & enemy_mask;
                 // print_bitboard(self.white_attack_masks[square.index() as usize]);
             }
             PieceColor::Black => {
                 board |= self.black_move_masks[square.index() as usize]
                     & !piece_mask
-                    & !(piece_mask & f_rank).shift(-1, 0);
+                    & !(blockers & f_rank).shift(-1, 0);
                 board |= self.black_attack_masks[square.index() as usize] & enemy_mask;
                 // print_bitboard(self.black_attack_masks[square.index() as usize]);
             }
@@ -180,9 +190,14 @@ pub struct SlidingPieceLookup<const N: usize> {
 }
 
 impl<const N: usize> SlidingPieceLookup<N> {
+    fn empty_table() -> Box<[Option<Bitboard>; N]> {
+        // Built on the heap, as the rook table is too large for a thread's stack.
+        vec![None; N].into_boxed_slice().try_into().unwrap()
+    }
+
     pub fn generate_rook() -> Self {
         let occupancy_masks = for_all_squares(rook_occupancy_mask);
-        let mut lookup_table = Box::new([None; N]);
+        let mut lookup_table = Self::empty_table();
 
         for i in 0..64 {
             let occupancy_mask = occupancy_masks[i];
@@ -204,7 +219,7 @@ impl<const N: usize> SlidingPieceLookup<N> {
 
     pub fn generate_bishop() -> Self {
         let occupancy_masks = for_all_squares(bishop_occupancy_mask);
-        let mut lookup_table = Box::new([None; N]);
+        let mut lookup_table = Self::empty_table();
 
         for i in 0..64 {
             let occupancy_mask = occupancy_masks[i];
@@ -1,25 +1,33 @@
 pub mod bitboards;
 pub mod board;
 pub mod castle;
+pub mod eval;
 pub mod lookup;
 pub mod magics;
 pub mod masks;
+pub mod movelist;
 pub mod movement;
+pub mod ordering;
+pub mod pawns;
+pub mod pgn;
 pub mod piece;
 pub mod position;
+pub mod san;
 pub mod search;
 pub mod transposition;
+pub mod uci;
 
 use crate::board::FastBoard;
-use crate::lookup::LookupTables;
+use crate::eval::EvalParams;
 use crate::movement::{CastleDirection, Move, Promotion};
+use crate::pgn::{Game, GameResult};
 use crate::piece::PieceColor;
 use crate::position::BoardPosition;
 
 use anyhow::bail;
 use dialoguer::{theme::ColorfulTheme, Input};
-use search::{EndState, MoveEngine};
-use std::time::Duration;
+use search::{EndState, MoveEngine, SearchLimits};
+use std::time::{Duration, Instant};
 
 fn parse_move(input: &str) -> anyhow::Result<BoardPosition> {
     let mut chars = input.chars();
@@ -29,17 +37,74 @@ fn parse_move(input: &str) -> anyhow::Result<BoardPosition> {
 }
 
 fn main() -> anyhow::Result<()> {
+    let mut args = std::env::args().skip(1).peekable();
+
+    // Evaluation parameters must be loaded before any board is created.
+    if args.peek().map(String::as_str) == Some("--params") {
+        args.next();
+        let Some(path) = args.next() else {
+            bail!("usage: --params <file>");
+        };
+        eval::set_params(EvalParams::load(path)?)?;
+    }
+
+    match args.next().as_deref() {
+        None => play(),
+        Some("uci") => uci::run(std::io::stdin().lock(), std::io::stdout()),
+        Some("perft") => perft(args),
+        Some(command) => bail!("unknown command `{}`", command),
+    }
+}
+
+/// Prints the perft count for each legal move, e.g. `perft 4 <fen>`.
+fn perft(mut args: impl Iterator<Item = String>) -> anyhow::Result<()> {
+    let Some(depth) = args.next() else {
+        bail!("usage: perft <depth> [fen]");
+    };
+    let depth = depth.parse()?;
+
+    let fen: Vec<_> = args.collect();
+    let mut board = if fen.is_empty() {
+        FastBoard::initial()
+    } else {
+        FastBoard::from_fen(&fen.join(" "))?
+    };
+
+    let start = Instant::now();
+    let divided = board.divide(depth);
+
+    for (board_move, nodes) in &divided {
+        println!("{}: {}", board_move.to_uci(board.next_to_move), nodes);
+    }
+
+    let total: u64 = divided.iter().map(|(_, nodes)| nodes).sum();
+    println!("\nNodes searched: {} ({:?})", total, start.elapsed());
+
+    Ok(())
+}
+
+fn play() -> anyhow::Result<()> {
     let mut move_engine = MoveEngine::new();
 
-    let fen: String = Input::with_theme(&ColorfulTheme::default())
-        .with_prompt("Initial FEN (empty for new game)")
+    let start: String = Input::with_theme(&ColorfulTheme::default())
+        .with_prompt("Initial FEN or PGN file (empty for new game)")
         .allow_empty(true)
         .interact_text()?;
 
-    let mut board = if fen.is_empty() {
+    // Comments on each move of the game, such as the engine's evaluations.
+    let mut comments = Vec::new();
+
+    let mut board = if start.is_empty() {
         FastBoard::initial()
+    } else if start.ends_with(".pgn") {
+        let pgn = std::fs::read_to_string(&start)?;
+        let Some(game) = pgn::parse_games(&pgn)?.into_iter().next() else {
+            bail!("no games in `{}`", start);
+        };
+        comments = game.moves.iter().map(|m| m.comment.clone()).collect();
+        game.positions()?.pop().unwrap()
     } else {
-        FastBoard::from_fen(&fen)?
+        FastBoard::from_fen(&start)?
     };
 
     // print!("User color [W/b]: ");
@@ -56,45 +121,50 @@ fn main() -> anyhow::Result<()> {
 
     if user_color == board.next_to_move {
         print_board(&board);
-        if check_end_state(&mut board, &mut move_engine)? {
+        if check_end_state(&mut board, &mut move_engine, &comments)? {
             return Ok(());
         }
 
-        user_move(&mut board)?;
+        user_move(&mut board, &mut comments)?;
     }
 
     loop {
         print_board(&board);
-        if check_end_state(&mut board, &mut move_engine)? {
+        if check_end_state(&mut board, &mut move_engine, &comments)? {
             return Ok(());
         }
 
         // COMPUTER MOVE
-        computer_move(&mut board, &mut move_engine)?;
+        computer_move(&mut board, &mut move_engine, &mut comments)?;
 
         print_board(&board);
-        if check_end_state(&mut board, &mut move_engine)? {
+        if check_end_state(&mut board, &mut move_engine, &comments)? {
             return Ok(());
         }
 
         // USER MOVE
-        user_move(&mut board)?;
+        user_move(&mut board, &mut comments)?;
     }
 }
 
 enum UserAction {
     Move(Move),
     Unmake,
+    Save(String),
 }
 
-fn parse_action(input: &str) -> anyhow::Result<UserAction> {
-    let mut words = input.trim().split(" ");
+fn parse_action(board: &mut FastBoard, input: &str) -> anyhow::Result<UserAction> {
+    let mut words = input.split_whitespace();
 
-    let first = words.next().unwrap();
+    let Some(first) = words.next() else {
+        bail!("no move entered");
+    };
 
     match first {
         "castle" => {
-            let second = words.next().unwrap();
+            let Some(second) = words.next() else {
+                bail!("expected `kingside` or `queenside`");
+            };
             let castle = Move::Castle(match second {
                 "queenside" => CastleDirection::QueenSide,
                 "kingside" => CastleDirection::KingSide,
@@ -105,8 +175,16 @@ fn parse_action(input: &str) -> anyhow::Result<UserAction> {
 
         "unmake" => Ok(UserAction::Unmake),
 
+        "save" => match words.next() {
+            Some(path) => Ok(UserAction::Save(path.into())),
+            None => bail!("usage: save <file>"),
+        },
+
         _ => {
-            let second = words.next().unwrap();
+            // A single word is a move in algebraic notation, e.g. `Nf3`.
+            let Some(second) = words.next() else {
+                return Ok(UserAction::Move(board.parse_san(first)?));
+            };
             let from = parse_move(first)?;
             let to = parse_move(second)?;
 
@@ -134,51 +212,147 @@ fn parse_action(input: &str) -> anyhow::Result<UserAction> {
     }
 }
 
-fn check_end_state(board: &mut FastBoard, engine: &mut MoveEngine) -> anyhow::Result<bool> {
+fn check_end_state(
+    board: &mut FastBoard,
+    engine: &mut MoveEngine,
+    comments: &[Option<String>],
+) -> anyhow::Result<bool> {
     let Some(end_state) = engine.get_end_state(board) else {
         return Ok(false);
     };
 
+    let result = match end_state {
+        EndState::Checkmate => match board.next_to_move {
+            PieceColor::White => GameResult::BlackWins,
+            PieceColor::Black => GameResult::WhiteWins,
+        },
+        _ => GameResult::Draw,
+    };
+
     match end_state {
         EndState::Checkmate => {
             println!("Checkmate! {:?} wins.", board.next_to_move.other())
         }
         EndState::Stalemate => println!("Stalemate!"),
         EndState::ThreeFoldRepetiiton => println!("Draw (3-fold)!"),
+        EndState::FiftyMoveRule => println!("Draw (50-move rule)!"),
+        EndState::InsufficientMaterial => println!("Draw (insufficient material)!"),
+    }
+
+    let path: String = Input::with_theme(&ColorfulTheme::default())
+        .with_prompt("Save game to (empty to skip)")
+        .allow_empty(true)
+        .interact_text()?;
+    if !path.is_empty() {
+        save_game(board, comments, result, &path)?;
     }
 
     Ok(true)
 }
 
-fn computer_move(board: &mut FastBoard, engine: &mut MoveEngine) -> anyhow::Result<()> {
+fn computer_move(
+    board: &mut FastBoard,
+    engine: &mut MoveEngine,
+    comments: &mut Vec<Option<String>>,
+) -> anyhow::Result<()> {
     println!("Searching for best move...");
-    let next_move = engine.iterative_deepening(board, Duration::from_secs(5));
+    let limits = SearchLimits {
+        max_time: Some(Duration::from_secs(5)),
+        max_depth: None,
+    };
+    let root = board.clone();
+    let Some(result) = engine.iterative_deepening(board, limits, |result| {
+        let pv = format_line(&root, &result.pv);
+        println!(
+            "Depth {} (seldepth {}): score {}, {} nodes in {:.2?}\n  {}",
+            result.depth,
+            result.seldepth,
+            result.score,
+            result.nodes,
+            result.time,
+            pv.join(" "),
+        );
+    }) else {
+        bail!("no legal moves available");
+    };
+
+    let next_move = result.best_move;
+    println!("Best move: {}", board.move_to_san(next_move));
+    println!(
+        "First move cutoff rate: {:.1}%",
+        engine.ordering_stats().first_move_cutoff_rate() * 100.0
+    );
 
-    println!("Best move: {}", next_move);
     board.make_move(next_move)?;
 
+    comments.resize(board.move_list.len() - 1, None);
+    comments.push(Some(pgn::eval_comment(result.score, result.depth)));
+
     Ok(())
 }
 
-fn user_move(board: &mut FastBoard) -> anyhow::Result<()> {
-    let input: String = Input::with_theme(&ColorfulTheme::default())
-        .with_prompt("Enter your move")
-        .interact_text()
-        .unwrap();
-
-    match parse_action(&input)? {
-        UserAction::Move(board_move) => {
-            board.make_move(board_move).unwrap();
-        }
-        UserAction::Unmake => {
-            board.unmake_last_move();
-            board.unmake_last_move();
+fn user_move(board: &mut FastBoard, comments: &mut Vec<Option<String>>) -> anyhow::Result<()> {
+    loop {
+        let input: String = Input::with_theme(&ColorfulTheme::default())
+            .with_prompt("Enter your move")
+            .interact_text()?;
+
+        match parse_action(board, &input) {
+            Ok(UserAction::Move(board_move)) => match board.make_move(board_move) {
+                Ok(()) => break,
+                Err(error) => println!("{}", error),
+            },
+            Ok(UserAction::Unmake) => {
+                board.unmake_last_move();
+                board.unmake_last_move();
+                break;
+            }
+            Ok(UserAction::Save(path)) => {
+                match save_game(board, comments, GameResult::Unknown, &path) {
+                    Ok(()) => println!("Saved game to {}", path),
+                    Err(error) => println!("{}", error),
+                }
+            }
+            Err(error) => println!("{}", error),
         }
     }
 
+    comments.resize(board.move_list.len(), None);
+
+    Ok(())
+}
+
+/// Writes the game played on the board to a PGN file.
+fn save_game(
+    board: &FastBoard,
+    comments: &[Option<String>],
+    result: GameResult,
+    path: &str,
+) -> anyhow::Result<()> {
+    let mut game = Game::from_board(board, result);
+    for (game_move, comment) in game.moves.iter_mut().zip(comments) {
+        game_move.comment = comment.clone();
+    }
+
+    std::fs::write(path, game.to_pgn()?)?;
+
     Ok(())
 }
 
+/// Formats a line of moves from the given position in algebraic notation.
+fn format_line(board: &FastBoard, moves: &[Move]) -> Vec<String> {
+    let mut board = board.clone();
+
+    moves
+        .iter()
+        .map(|&board_move| {
+            let san = board.move_to_san(board_move);
+            board.make_move(board_move).unwrap();
+            san
+        })
+        .collect()
+}
+
 pub(crate) fn print_board(board: &FastBoard) {
     println!("Board State: {}\n", board.to_fen());
 
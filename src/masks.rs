@@ -1,22 +1,8 @@
 use crate::{bitboards::Bitboard, piece::PieceColor, position::BoardPosition};
 
 pub fn pawn_attack_mask(position: BoardPosition, color: PieceColor) -> Bitboard {
-    let pos = Bitboard::from_pos(&position);
-    let mut board = Bitboard::empty();
-
-    let rank_offset = match color {
-        PieceColor::White => 1,
-        PieceColor::Black => -1,
-    };
-
-    if position.file() != 0 {
-        board |= pos.shift(rank_offset, -1);
-    }
-    if position.file() != 7 {
-        board |= pos.shift(rank_offset, 1);
-    }
-
-    board
+    let forward = Bitboard::from_pos(&position).forward(color);
+    forward.east() | forward.west()
 }
 
 pub fn pawn_move_mask(position: BoardPosition, color: PieceColor) -> Bitboard {
@@ -42,42 +28,19 @@ pub fn pawn_move_mask(position: BoardPosition, color: PieceColor) -> Bitboard {
 
 pub fn knight_move_mask(position: BoardPosition) -> Bitboard {
     let pos = Bitboard::from_pos(&position);
-    let mut board = Bitboard::empty();
-
-    if position.file() > 1 {
-        board |= pos.shift(-1, -2) | pos.shift(1, -2);
-    }
-
-    if position.file() > 0 {
-        board |= pos.shift(-2, -1) | pos.shift(2, -1);
-    }
-
-    if position.file() < 7 {
-        board |= pos.shift(-2, 1) | pos.shift(2, 1);
-    }
 
-    if position.file() < 6 {
-        board |= pos.shift(-1, 2) | pos.shift(1, 2);
-    }
+    // One file across and two ranks up or down, or two files across and one rank.
+    let one_file = pos.east() | pos.west();
+    let two_files = pos.east().east() | pos.west().west();
 
-    board
+    one_file.north().north() | one_file.south().south() | two_files.north() | two_files.south()
 }
 
 pub fn king_move_mask(position: BoardPosition) -> Bitboard {
     let pos = Bitboard::from_pos(&position);
-    let mut board = Bitboard::empty();
+    let row = pos | pos.east() | pos.west();
 
-    board |= pos.shift(-1, 0) | pos.shift(1, 0);
-
-    if position.file() != 0 {
-        board |= pos.shift(-1, -1) | pos.shift(0, -1) | pos.shift(1, -1);
-    }
-
-    if position.file() != 7 {
-        board |= pos.shift(-1, 1) | pos.shift(0, 1) | pos.shift(1, 1);
-    }
-
-    board
+    (row | row.north() | row.south()) & !pos
 }
 
 pub fn bishop_occupancy_mask(position: BoardPosition) -> Bitboard {
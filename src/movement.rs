@@ -1,6 +1,11 @@
-use std::fmt::Display;
+use std::{fmt::Display, num::NonZeroU16};
 
-use crate::{castle::CastlingRights, piece::PieceType, position::BoardPosition};
+use crate::{
+    board::FastBoard,
+    castle::CastlingRights,
+    piece::{PieceColor, PieceType},
+    position::BoardPosition,
+};
 
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
 pub enum Move {
@@ -21,10 +26,10 @@ impl Display for Move {
                 promotion,
             } => {
                 let promotion = promotion
-                    .map(|p| format!("(promoted to {:?})", p))
+                    .map(|p| format!(" (promoted to {:?})", p))
                     .unwrap_or("".into());
 
-                write!(f, "{} -> {} {}", from, to, promotion)
+                write!(f, "{} -> {}{}", from, to, promotion)
             }
 
             Move::Castle(castle_direction) => match castle_direction {
@@ -35,6 +40,175 @@ impl Display for Move {
     }
 }
 
+impl Move {
+    /// Formats the move in long algebraic notation (e.g. `e2e4`, `e7e8q`), with castling
+    /// written as the king's move (e.g. `e1g1`) for the given side.
+    pub fn to_uci(&self, color: PieceColor) -> String {
+        match self {
+            Move::Direct {
+                from,
+                to,
+                promotion,
+            } => {
+                let promotion = match promotion {
+                    None => "",
+                    Some(Promotion::Queen) => "q",
+                    Some(Promotion::Rook) => "r",
+                    Some(Promotion::Bishop) => "b",
+                    Some(Promotion::Knight) => "n",
+                };
+
+                format!("{}{}{}", from, to, promotion)
+            }
+            Move::Castle(direction) => {
+                let rank = match color {
+                    PieceColor::White => '1',
+                    PieceColor::Black => '8',
+                };
+                let file = match direction {
+                    CastleDirection::QueenSide => 'c',
+                    CastleDirection::KingSide => 'g',
+                };
+
+                format!("e{}{}{}", rank, file, rank)
+            }
+        }
+    }
+}
+
+/// A move packed into 16 bits as origin (6 bits) | destination (6 bits) | flags (4 bits),
+/// for the tables kept by the search. Unlike `Move`, it also records whether the move
+/// captures, which takes the board to work out.
+///
+/// Castling is stored as the king's move. No move starts and ends on a1, so the packed
+/// value is never zero and `Option<PackedMove>` fits in 16 bits too.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+pub struct PackedMove(NonZeroU16);
+
+impl PackedMove {
+    const KING_CASTLE: u16 = 0b0010;
+    const QUEEN_CASTLE: u16 = 0b0011;
+    const CAPTURE: u16 = 0b0100;
+    const EN_PASSANT: u16 = 0b0101;
+    /// Set for promotions, with the promoted piece in the two lowest bits.
+    const PROMOTION: u16 = 0b1000;
+
+    /// Packs a move to be made on the given board.
+    pub fn new(board: &FastBoard, board_move: Move) -> Self {
+        let (from, to, flags) = match board_move {
+            Move::Direct {
+                from,
+                to,
+                promotion,
+            } => {
+                let moved = board.mailbox.get(&from).map(|piece| piece.kind);
+                let captures = board.mailbox.get(&to).is_some();
+
+                let flags =
+                    if moved == Some(PieceType::Pawn) && from.file() != to.file() && !captures {
+                        Self::EN_PASSANT
+                    } else {
+                        let capture = if captures { Self::CAPTURE } else { 0 };
+                        let promotion = match promotion {
+                            None => 0,
+                            Some(Promotion::Knight) => Self::PROMOTION,
+                            Some(Promotion::Bishop) => Self::PROMOTION | 1,
+                            Some(Promotion::Rook) => Self::PROMOTION | 2,
+                            Some(Promotion::Queen) => Self::PROMOTION | 3,
+                        };
+
+                        capture | promotion
+                    };
+
+                (from.index(), to.index(), flags)
+            }
+            Move::Castle(direction) => {
+                let rank = match board.next_to_move {
+                    PieceColor::White => 0,
+                    PieceColor::Black => 7,
+                };
+                let (file, flags) = match direction {
+                    CastleDirection::KingSide => (6, Self::KING_CASTLE),
+                    CastleDirection::QueenSide => (2, Self::QUEEN_CASTLE),
+                };
+
+                (rank * 8 + 4, rank * 8 + file, flags)
+            }
+        };
+
+        let packed = from as u16 | (to as u16) << 6 | flags << 12;
+        Self(NonZeroU16::new(packed).expect("a move never starts and ends on a1"))
+    }
+
+    /// Restores a move from its packed bits, e.g. as stored in the transposition table.
+    pub fn from_bits(bits: u16) -> Option<Self> {
+        NonZeroU16::new(bits).map(Self)
+    }
+
+    pub fn bits(&self) -> u16 {
+        self.0.get()
+    }
+
+    pub fn from(&self) -> BoardPosition {
+        BoardPosition::from_index((self.bits() & 0x3f) as u8)
+    }
+
+    pub fn to(&self) -> BoardPosition {
+        BoardPosition::from_index((self.bits() >> 6 & 0x3f) as u8)
+    }
+
+    fn flags(&self) -> u16 {
+        self.bits() >> 12
+    }
+
+    pub fn is_capture(&self) -> bool {
+        self.flags() & Self::CAPTURE != 0
+    }
+
+    pub fn is_en_passant(&self) -> bool {
+        self.flags() == Self::EN_PASSANT
+    }
+
+    pub fn castle(&self) -> Option<CastleDirection> {
+        match self.flags() {
+            Self::KING_CASTLE => Some(CastleDirection::KingSide),
+            Self::QUEEN_CASTLE => Some(CastleDirection::QueenSide),
+            _ => None,
+        }
+    }
+
+    pub fn promotion(&self) -> Option<Promotion> {
+        if self.flags() & Self::PROMOTION == 0 {
+            return None;
+        }
+
+        Some(match self.flags() & 3 {
+            0 => Promotion::Knight,
+            1 => Promotion::Bishop,
+            2 => Promotion::Rook,
+            _ => Promotion::Queen,
+        })
+    }
+
+    /// Whether the move neither captures nor promotes.
+    pub fn is_quiet(&self) -> bool {
+        self.flags() & (Self::CAPTURE | Self::PROMOTION) == 0
+    }
+}
+
+impl From<PackedMove> for Move {
+    fn from(packed: PackedMove) -> Self {
+        match packed.castle() {
+            Some(direction) => Move::Castle(direction),
+            None => Move::Direct {
+                from: packed.from(),
+                to: packed.to(),
+                promotion: packed.promotion(),
+            },
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
 pub enum CastleDirection {
     QueenSide,
@@ -53,7 +227,7 @@ impl Promotion {
     pub fn piece_type(&self) -> PieceType {
         match self {
             Promotion::Queen => PieceType::Queen,
-            Promotion::Knight => PieceType::King,
+            Promotion::Knight => PieceType::Knight,
             Promotion::Rook => PieceType::Rook,
             Promotion::Bishop => PieceType::Bishop,
         }
@@ -64,22 +238,30 @@ impl Promotion {
 pub struct PastMove {
     pub move_made: Move,
     pub captured: Option<PieceType>,
+    /// Whether the captured pawn was taken en passant, i.e. it stood beside the
+    /// destination square rather than on it.
+    pub en_passant_capture: bool,
     pub previous_castling_rights: Option<CastlingRights>,
     pub previous_en_passant: Option<u8>,
+    pub previous_halfmove_clock: u32,
 }
 
 impl PastMove {
     pub fn new(
         move_made: Move,
         captured: Option<PieceType>,
+        en_passant_capture: bool,
         previous_castling_rights: Option<CastlingRights>,
         previous_en_passant: Option<u8>,
+        previous_halfmove_clock: u32,
     ) -> Self {
         Self {
             move_made,
             captured,
+            en_passant_capture,
             previous_castling_rights,
             previous_en_passant,
+            previous_halfmove_clock,
         }
     }
 }
@@ -0,0 +1,289 @@
+use crate::{
+    board::FastBoard,
+    movelist::{MoveList, MAX_MOVES},
+    movement::{Move, PackedMove},
+    piece::{PieceColor, PieceType},
+};
+
+/// Deepest ply for which killer moves are kept.
+pub const MAX_PLY: usize = 128;
+
+/// Quiet move heuristics learned from beta cutoffs during a search.
+#[derive(Debug, Clone)]
+pub struct MoveOrdering {
+    killers: [[Option<PackedMove>; 2]; MAX_PLY],
+    history: Box<[[[i32; 64]; 64]; 2]>,
+    stats: OrderingStats,
+}
+
+impl Default for MoveOrdering {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MoveOrdering {
+    pub fn new() -> Self {
+        Self {
+            killers: [[None; 2]; MAX_PLY],
+            history: Box::new([[[0; 64]; 64]; 2]),
+            stats: OrderingStats::default(),
+        }
+    }
+
+    /// Prepares for a new search, forgetting killers (which are specific to the old
+    /// root) and decaying the history so that recent cutoffs dominate.
+    pub fn new_search(&mut self) {
+        self.killers = [[None; 2]; MAX_PLY];
+        self.stats = OrderingStats::default();
+
+        for scores in self.history.iter_mut().flatten() {
+            for score in scores.iter_mut() {
+                *score /= 2;
+            }
+        }
+    }
+
+    pub fn stats(&self) -> &OrderingStats {
+        &self.stats
+    }
+
+    /// Records that `board_move` caused a beta cutoff at the given node. `first` is
+    /// whether it was the first legal move tried.
+    pub fn record_cutoff(
+        &mut self,
+        board: &FastBoard,
+        board_move: Move,
+        depth: u32,
+        ply: usize,
+        first: bool,
+    ) {
+        self.stats.cutoffs += 1;
+        if first {
+            self.stats.first_move_cutoffs += 1;
+        }
+
+        let packed = PackedMove::new(board, board_move);
+        if !packed.is_quiet() {
+            return;
+        }
+
+        if let Some(killers) = self.killers.get_mut(ply) {
+            if killers[0] != Some(packed) {
+                killers[1] = killers[0];
+                killers[0] = Some(packed);
+            }
+        }
+
+        if let Some((from, to)) = history_squares(board_move) {
+            let score = &mut self.history[board.next_to_move.index()][from][to];
+            *score += (depth * depth) as i32;
+        }
+    }
+
+    fn killers(&self, ply: usize) -> [Option<Move>; 2] {
+        let killers = self.killers.get(ply).copied().unwrap_or_default();
+        killers.map(|killer| killer.map(Move::from))
+    }
+
+    fn history(&self, color: PieceColor, board_move: Move) -> i32 {
+        history_squares(board_move).map_or(0, |(from, to)| self.history[color.index()][from][to])
+    }
+}
+
+/// How often the first move searched was good enough to cause a beta cutoff.
+#[derive(Debug, Clone, Default)]
+pub struct OrderingStats {
+    pub cutoffs: u64,
+    pub first_move_cutoffs: u64,
+}
+
+impl OrderingStats {
+    pub fn first_move_cutoff_rate(&self) -> f64 {
+        if self.cutoffs == 0 {
+            return 0.0;
+        }
+
+        self.first_move_cutoffs as f64 / self.cutoffs as f64
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Stage {
+    TtMove,
+    GoodCaptures,
+    Killers,
+    Quiets,
+    BadCaptures,
+    Done,
+}
+
+/// Yields the moves of a position in stages: the transposition table move, then
+/// captures and promotions which do not lose material by MVV-LVA, then killer moves,
+/// then the remaining quiet moves by their history score, and finally the captures
+/// which lose material in the static exchange.
+pub struct MovePicker {
+    stage: Stage,
+    tt_move: Option<Move>,
+    killers: [Option<Move>; 2],
+    /// Good captures, then quiet moves, then bad captures, each with its score at the
+    /// same index.
+    moves: MoveList,
+    scores: [i32; MAX_MOVES],
+    good_captures_end: usize,
+    quiets_end: usize,
+    next: usize,
+}
+
+impl MovePicker {
+    pub fn new(
+        board: &FastBoard,
+        moves: MoveList,
+        tt_move: Option<Move>,
+        ordering: &MoveOrdering,
+        ply: usize,
+    ) -> Self {
+        // Only keep the hash and killer moves if they are available in this position.
+        let tt_move = tt_move.filter(|tt_move| moves.contains(tt_move));
+        let killers = ordering.killers(ply).map(|killer| {
+            killer.filter(|killer| Some(*killer) != tt_move && moves.contains(killer))
+        });
+
+        let mut good_captures = MoveList::new();
+        let mut quiets = MoveList::new();
+        let mut bad_captures = MoveList::new();
+        for board_move in moves {
+            if Some(board_move) == tt_move || killers.contains(&Some(board_move)) {
+                continue;
+            }
+
+            if PackedMove::new(board, board_move).is_quiet() {
+                quiets.push(board_move);
+            } else if board.static_exchange(board_move) >= 0 {
+                good_captures.push(board_move);
+            } else {
+                bad_captures.push(board_move);
+            }
+        }
+
+        let good_captures_end = good_captures.len();
+        let quiets_end = good_captures_end + quiets.len();
+
+        let mut scores = [0; MAX_MOVES];
+        for (score, &capture) in scores.iter_mut().zip(good_captures.iter()) {
+            *score = mvv_lva(board, capture);
+        }
+        for (score, &quiet) in scores[good_captures_end..].iter_mut().zip(quiets.iter()) {
+            *score = ordering.history(board.next_to_move, quiet);
+        }
+        for (score, &capture) in scores[quiets_end..].iter_mut().zip(bad_captures.iter()) {
+            *score = mvv_lva(board, capture);
+        }
+
+        let mut moves = good_captures;
+        moves.extend(quiets);
+        moves.extend(bad_captures);
+
+        Self {
+            stage: Stage::TtMove,
+            tt_move,
+            killers,
+            moves,
+            scores,
+            good_captures_end,
+            quiets_end,
+            next: 0,
+        }
+    }
+
+    /// Takes the highest scoring move before `end`. Selecting lazily is cheaper than
+    /// sorting, as most nodes cut off after the first few moves.
+    fn pick_best(&mut self, end: usize) -> Option<Move> {
+        let next = self.next;
+        let best = (next..end).max_by_key(|&i| self.scores[i])?;
+
+        self.moves.swap(next, best);
+        self.scores.swap(next, best);
+        self.next += 1;
+
+        Some(self.moves[next])
+    }
+}
+
+impl Iterator for MovePicker {
+    type Item = Move;
+
+    fn next(&mut self) -> Option<Move> {
+        loop {
+            match self.stage {
+                Stage::TtMove => {
+                    self.stage = Stage::GoodCaptures;
+                    if self.tt_move.is_some() {
+                        return self.tt_move;
+                    }
+                }
+                Stage::GoodCaptures => match self.pick_best(self.good_captures_end) {
+                    Some(capture) => return Some(capture),
+                    None => self.stage = Stage::Killers,
+                },
+                Stage::Killers => match self.killers.iter_mut().find_map(|killer| killer.take()) {
+                    Some(killer) => return Some(killer),
+                    None => self.stage = Stage::Quiets,
+                },
+                Stage::Quiets => match self.pick_best(self.quiets_end) {
+                    Some(quiet) => return Some(quiet),
+                    None => self.stage = Stage::BadCaptures,
+                },
+                Stage::BadCaptures => match self.pick_best(self.moves.len()) {
+                    Some(capture) => return Some(capture),
+                    None => self.stage = Stage::Done,
+                },
+                Stage::Done => return None,
+            }
+        }
+    }
+}
+
+/// Most valuable victim, least valuable attacker: prefer winning the biggest piece
+/// with the smallest one. Promotions count as capturing the promoted piece.
+fn mvv_lva(board: &FastBoard, board_move: Move) -> i32 {
+    let Move::Direct {
+        from,
+        to,
+        promotion,
+    } = board_move
+    else {
+        return 0;
+    };
+
+    let victim = match board.mailbox.get(&to) {
+        Some(piece) => piece_rank(piece.kind),
+        None if promotion.is_none() => piece_rank(PieceType::Pawn),
+        None => 0,
+    };
+    let promoted = promotion.map_or(0, |promotion| piece_rank(promotion.piece_type()));
+    let attacker = board
+        .mailbox
+        .get(&from)
+        .map_or(0, |piece| piece_rank(piece.kind));
+
+    (victim + promoted) * 8 - attacker
+}
+
+fn piece_rank(kind: PieceType) -> i32 {
+    match kind {
+        PieceType::Pawn => 1,
+        PieceType::Knight => 2,
+        PieceType::Bishop => 3,
+        PieceType::Rook => 4,
+        PieceType::Queen => 5,
+        PieceType::King => 6,
+    }
+}
+
+fn history_squares(board_move: Move) -> Option<(usize, usize)> {
+    match board_move {
+        Move::Direct { from, to, .. } => Some((from.index() as usize, to.index() as usize)),
+        Move::Castle(_) => None,
+    }
+}
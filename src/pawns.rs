@@ -0,0 +1,194 @@
+use crate::{
+    bitboards::{Bitboard, FILES, RANKS},
+    eval::EvalParams,
+    lookup::LOOKUP,
+    piece::PieceColor,
+    position::BoardPosition,
+    transposition::ZobristKey,
+};
+
+/// Number of entries in the pawn hash table. Must be a power of two.
+const PAWN_TABLE_SIZE: usize = 1 << 14;
+
+/// Pawn structure scores, which only depend on the positions of the pawns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PawnStructure {
+    /// Midgame and endgame scores from White's point of view.
+    pub midgame: i32,
+    pub endgame: i32,
+    /// Passed pawns for each colour, indexed white then black.
+    pub passed: [Bitboard; 2],
+}
+
+impl PawnStructure {
+    pub fn evaluate(white_pawns: Bitboard, black_pawns: Bitboard, params: &EvalParams) -> Self {
+        let mut structure = Self {
+            midgame: 0,
+            endgame: 0,
+            passed: [Bitboard::empty(); 2],
+        };
+
+        for (color, pawns, enemy_pawns, sign) in [
+            (PieceColor::White, white_pawns, black_pawns, 1),
+            (PieceColor::Black, black_pawns, white_pawns, -1),
+        ] {
+            let (midgame, endgame) = structure.evaluate_side(color, pawns, enemy_pawns, params);
+            structure.midgame += sign * midgame;
+            structure.endgame += sign * endgame;
+        }
+
+        structure
+    }
+
+    /// Bonuses for the passed pawns of one side, reduced when a piece stands in the way.
+    pub fn passed_pawn_bonus(
+        &self,
+        color: PieceColor,
+        occupied: Bitboard,
+        params: &EvalParams,
+    ) -> (i32, i32) {
+        let mut midgame = 0;
+        let mut endgame = 0;
+
+        for square in self.passed[color.index()].filled_indices() {
+            let square = BoardPosition::from_index(square);
+            let rank = relative_rank(color, &square) as usize;
+            let stop = stop_square(color, &square);
+
+            let blocked = (occupied & Bitboard::from_pos(&stop)).any();
+            let divisor = if blocked { 2 } else { 1 };
+
+            midgame += params.passed_midgame_bonus[rank] / divisor;
+            endgame += params.passed_endgame_bonus[rank] / divisor;
+        }
+
+        (midgame, endgame)
+    }
+
+    /// Penalties for one side's pawns, recording its passed pawns along the way.
+    fn evaluate_side(
+        &mut self,
+        color: PieceColor,
+        pawns: Bitboard,
+        enemy_pawns: Bitboard,
+        params: &EvalParams,
+    ) -> (i32, i32) {
+        let mut midgame = 0;
+        let mut endgame = 0;
+
+        for file in FILES {
+            let count = (pawns & file).count() as i32;
+            if count > 1 {
+                midgame -= params.doubled_penalty[0] * (count - 1);
+                endgame -= params.doubled_penalty[1] * (count - 1);
+            }
+        }
+
+        for square in pawns.filled_indices() {
+            let square = BoardPosition::from_index(square);
+            let file = square.file();
+            let neighbours = adjacent_files(file);
+
+            if (pawns & neighbours).is_empty() {
+                midgame -= params.isolated_penalty[0];
+                endgame -= params.isolated_penalty[1];
+            } else if is_backward(color, &square, pawns, enemy_pawns) {
+                midgame -= params.backward_penalty[0];
+                endgame -= params.backward_penalty[1];
+            }
+
+            let front_span = front_span(color, &square);
+            let passed_mask = front_span & (FILES[file as usize] | neighbours);
+            if (enemy_pawns & passed_mask).is_empty() {
+                self.passed[color.index()] |= Bitboard::from_pos(&square);
+            }
+        }
+
+        (midgame, endgame)
+    }
+}
+
+/// A cache of pawn structure evaluations, keyed by a Zobrist key of the pawns alone.
+/// Pawn structures change rarely during a search, so almost every lookup hits.
+#[derive(Debug, Clone)]
+pub struct PawnTable {
+    entries: Vec<Option<(ZobristKey, PawnStructure)>>,
+}
+
+impl Default for PawnTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PawnTable {
+    pub fn new() -> Self {
+        Self {
+            entries: vec![None; PAWN_TABLE_SIZE],
+        }
+    }
+
+    pub fn get_or_insert(
+        &mut self,
+        pawn_key: ZobristKey,
+        evaluate: impl FnOnce() -> PawnStructure,
+    ) -> PawnStructure {
+        let entry = &mut self.entries[pawn_key.inner() as usize & (PAWN_TABLE_SIZE - 1)];
+
+        match entry {
+            Some((key, structure)) if *key == pawn_key => *structure,
+            _ => {
+                let structure = evaluate();
+                *entry = Some((pawn_key, structure));
+                structure
+            }
+        }
+    }
+}
+
+/// A pawn is backward if no friendly pawn on a neighbouring file can come up to support
+/// it, and it cannot safely advance because an enemy pawn guards the square in front.
+fn is_backward(
+    color: PieceColor,
+    square: &BoardPosition,
+    pawns: Bitboard,
+    enemy_pawns: Bitboard,
+) -> bool {
+    let supporters = adjacent_files(square.file()) & !front_span(color, square);
+    if (pawns & supporters).any() {
+        return false;
+    }
+
+    let stop = stop_square(color, square);
+    (LOOKUP.pawn_attacks(&stop, color) & enemy_pawns).any()
+}
+
+fn adjacent_files(file: u8) -> Bitboard {
+    let file = FILES[file as usize];
+    file.east() | file.west()
+}
+
+/// All squares on ranks in front of the pawn, from its own point of view.
+fn front_span(color: PieceColor, square: &BoardPosition) -> Bitboard {
+    let mut span = Bitboard::empty();
+    let mut rank = RANKS[square.rank() as usize].forward(color);
+    while rank.any() {
+        span |= rank;
+        rank = rank.forward(color);
+    }
+    span
+}
+
+fn stop_square(color: PieceColor, square: &BoardPosition) -> BoardPosition {
+    match color {
+        PieceColor::White => BoardPosition::from_rank_file(square.rank() + 1, square.file()),
+        PieceColor::Black => BoardPosition::from_rank_file(square.rank() - 1, square.file()),
+    }
+}
+
+fn relative_rank(color: PieceColor, square: &BoardPosition) -> u8 {
+    match color {
+        PieceColor::White => square.rank(),
+        PieceColor::Black => 7 - square.rank(),
+    }
+}
@@ -0,0 +1,449 @@
+use std::fmt::Display;
+
+use crate::{
+    board::{FastBoard, FenError, MoveError},
+    movement::Move,
+    piece::PieceColor,
+    san::SanError,
+    search::Score,
+};
+
+/// Games are written with lines no longer than this, as the PGN standard recommends.
+const MAX_LINE_LENGTH: usize = 79;
+
+/// The tags every game must have, in the order they are written.
+const SEVEN_TAG_ROSTER: [(&str, &str); 7] = [
+    ("Event", "?"),
+    ("Site", "?"),
+    ("Date", "????.??.??"),
+    ("Round", "?"),
+    ("White", "?"),
+    ("Black", "?"),
+    ("Result", "*"),
+];
+
+#[derive(thiserror::Error, Debug)]
+pub enum PgnError {
+    #[error("Unterminated {0}")]
+    Unterminated(&'static str),
+    #[error("Invalid tag `{0}`")]
+    InvalidTag(String),
+    #[error("Unbalanced variation")]
+    UnbalancedVariation,
+    #[error("Invalid FEN tag: {0}")]
+    InvalidFen(#[from] FenError),
+    #[error("Move {number} `{san}`: {source}")]
+    InvalidMove {
+        number: u32,
+        san: String,
+        source: SanError,
+    },
+    #[error("Illegal move: {0}")]
+    IllegalMove(#[from] MoveError),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameResult {
+    WhiteWins,
+    BlackWins,
+    Draw,
+    /// The game is still in progress, or its result is unknown.
+    Unknown,
+}
+
+impl GameResult {
+    fn parse(text: &str) -> Option<Self> {
+        match text {
+            "1-0" => Some(GameResult::WhiteWins),
+            "0-1" => Some(GameResult::BlackWins),
+            "1/2-1/2" => Some(GameResult::Draw),
+            "*" => Some(GameResult::Unknown),
+            _ => None,
+        }
+    }
+}
+
+impl Display for GameResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let result = match self {
+            GameResult::WhiteWins => "1-0",
+            GameResult::BlackWins => "0-1",
+            GameResult::Draw => "1/2-1/2",
+            GameResult::Unknown => "*",
+        };
+
+        write!(f, "{}", result)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameMove {
+    pub board_move: Move,
+    /// The comment following the move, e.g. the engine's evaluation.
+    pub comment: Option<String>,
+}
+
+/// A game in the main line only: variations are dropped when parsing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Game {
+    /// Tag pairs in the order they are written. The `Result` tag is kept in sync with
+    /// `result` when writing.
+    pub tags: Vec<(String, String)>,
+    pub moves: Vec<GameMove>,
+    pub result: GameResult,
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Game {
+    /// An empty game from the starting position, with placeholder tags.
+    pub fn new() -> Self {
+        Self {
+            tags: SEVEN_TAG_ROSTER
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            moves: Vec::new(),
+            result: GameResult::Unknown,
+        }
+    }
+
+    /// The game played on the board so far, from the position it was set up in.
+    pub fn from_board(board: &FastBoard, result: GameResult) -> Self {
+        let mut start = board.clone();
+        let mut moves = Vec::new();
+        while let Some(past_move) = start.move_list.last() {
+            moves.push(GameMove {
+                board_move: past_move.move_made,
+                comment: None,
+            });
+            start.unmake_last_move();
+        }
+        moves.reverse();
+
+        let mut game = Self {
+            moves,
+            result,
+            ..Self::new()
+        };
+
+        let fen = start.to_fen();
+        if fen != FastBoard::initial().to_fen() {
+            game.set_tag("SetUp", "1");
+            game.set_tag("FEN", &fen);
+        }
+
+        game
+    }
+
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(tag, _)| tag == name) {
+            Some((_, existing)) => *existing = value.into(),
+            None => self.tags.push((name.into(), value.into())),
+        }
+    }
+
+    /// The position the game starts from, given by the `FEN` tag if there is one.
+    pub fn initial_board(&self) -> Result<FastBoard, FenError> {
+        match self.tag("FEN") {
+            Some(fen) => FastBoard::from_fen(fen),
+            None => Ok(FastBoard::initial()),
+        }
+    }
+
+    /// Every position of the game, from the initial position to the final one. Each
+    /// board carries the moves leading to it.
+    pub fn positions(&self) -> Result<Vec<FastBoard>, PgnError> {
+        let mut board = self.initial_board()?;
+        let mut positions = vec![board.clone()];
+
+        for game_move in &self.moves {
+            board.make_move(game_move.board_move)?;
+            positions.push(board.clone());
+        }
+
+        Ok(positions)
+    }
+
+    /// Writes the game in PGN export format.
+    pub fn to_pgn(&self) -> Result<String, PgnError> {
+        let mut pgn = String::new();
+
+        let roster = SEVEN_TAG_ROSTER.iter().map(|(name, _)| *name);
+        let extra = self
+            .tags
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .filter(|name| !SEVEN_TAG_ROSTER.iter().any(|(tag, _)| tag == name));
+
+        for name in roster.chain(extra) {
+            let value = match name {
+                "Result" => self.result.to_string(),
+                _ => self.tag(name).unwrap_or("?").to_string(),
+            };
+            let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+            pgn.push_str(&format!("[{} \"{}\"]\n", name, value));
+        }
+        pgn.push('\n');
+
+        let mut board = self.initial_board()?;
+        let mut tokens = Vec::new();
+        let mut after_comment = false;
+
+        for game_move in &self.moves {
+            let number = board.fullmoves();
+            match board.next_to_move() {
+                PieceColor::White => tokens.push(format!("{}.", number)),
+                // Black's moves are numbered at the start of the game or after a comment.
+                PieceColor::Black if tokens.is_empty() || after_comment => {
+                    tokens.push(format!("{}...", number))
+                }
+                PieceColor::Black => {}
+            }
+
+            tokens.push(board.move_to_san(game_move.board_move));
+            board.make_move(game_move.board_move)?;
+
+            after_comment = game_move.comment.is_some();
+            if let Some(comment) = &game_move.comment {
+                tokens.push(format!("{{{}}}", comment.replace('}', ")")));
+            }
+        }
+        tokens.push(self.result.to_string());
+
+        let mut line = String::new();
+        for token in tokens {
+            if !line.is_empty() && line.len() + 1 + token.len() > MAX_LINE_LENGTH {
+                pgn.push_str(&line);
+                pgn.push('\n');
+                line.clear();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(&token);
+        }
+        pgn.push_str(&line);
+        pgn.push('\n');
+
+        Ok(pgn)
+    }
+}
+
+/// Formats an engine evaluation for a move comment, from the mover's point of view,
+/// e.g. `+0.35/12` or `-M3/20`.
+pub fn eval_comment(score: Score, depth: u32) -> String {
+    match score.mate_in_moves() {
+        Some(moves) if moves < 0 => format!("-M{}/{}", -moves, depth),
+        Some(moves) => format!("+M{}/{}", moves, depth),
+        None => format!("{:+.2}/{}", score.value() as f64 / 100.0, depth),
+    }
+}
+
+enum Token<'a> {
+    Tag(String, String),
+    Comment(String),
+    VariationStart,
+    VariationEnd,
+    Symbol(&'a str),
+}
+
+/// Parses every game in a PGN file. Comments after a move are kept, while numeric
+/// annotation glyphs and variations are skipped.
+pub fn parse_games(text: &str) -> Result<Vec<Game>, PgnError> {
+    let mut games = Vec::new();
+
+    let mut game = Game::new();
+    let mut board: Option<FastBoard> = None;
+    let mut started = false;
+    let mut variation_depth = 0;
+
+    for token in tokenize(text)? {
+        match token {
+            Token::VariationStart => variation_depth += 1,
+            Token::VariationEnd if variation_depth == 0 => {
+                return Err(PgnError::UnbalancedVariation)
+            }
+            Token::VariationEnd => variation_depth -= 1,
+            _ if variation_depth > 0 => {}
+
+            Token::Tag(name, value) => {
+                // Tags after movetext start the next game, even without a result.
+                if board.is_some() {
+                    games.push(std::mem::take(&mut game));
+                    board = None;
+                }
+                game.set_tag(&name, &value);
+                started = true;
+            }
+
+            Token::Comment(comment) => {
+                if let Some(last) = game.moves.last_mut() {
+                    last.comment = Some(match last.comment.take() {
+                        Some(existing) => format!("{} {}", existing, comment),
+                        None => comment,
+                    });
+                }
+            }
+
+            Token::Symbol(symbol) => {
+                if let Some(result) = GameResult::parse(symbol) {
+                    game.result = result;
+                    game.set_tag("Result", &result.to_string());
+                    games.push(std::mem::take(&mut game));
+                    board = None;
+                    started = false;
+                    continue;
+                }
+
+                // Move numbers may be attached to the move, as in `12...Nf6`.
+                let san = strip_move_number(symbol);
+                if san.is_empty()
+                    || symbol.starts_with('$')
+                    || san.chars().all(|c| "!?".contains(c))
+                {
+                    continue;
+                }
+
+                let board = match &mut board {
+                    Some(board) => board,
+                    None => board.insert(game.initial_board()?),
+                };
+
+                let board_move = board
+                    .parse_san(san)
+                    .map_err(|source| PgnError::InvalidMove {
+                        number: board.fullmoves(),
+                        san: san.into(),
+                        source,
+                    })?;
+                board.make_move(board_move)?;
+
+                game.moves.push(GameMove {
+                    board_move,
+                    comment: None,
+                });
+                started = true;
+            }
+        }
+    }
+
+    if variation_depth > 0 {
+        return Err(PgnError::UnbalancedVariation);
+    }
+    if started {
+        games.push(game);
+    }
+
+    Ok(games)
+}
+
+/// Removes a move number such as `12.` or `12...` from the start of a symbol. Digits
+/// without a following dot are kept, as in zero-style castling (`0-0`).
+fn strip_move_number(symbol: &str) -> &str {
+    let rest = symbol.trim_start_matches(|c: char| c.is_ascii_digit());
+    if rest.len() < symbol.len() && rest.starts_with('.') {
+        rest.trim_start_matches('.')
+    } else {
+        symbol
+    }
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token<'_>>, PgnError> {
+    let mut tokens = Vec::new();
+    let mut rest = text;
+
+    while let Some(c) = rest.chars().next() {
+        match c {
+            _ if c.is_whitespace() => rest = &rest[c.len_utf8()..],
+
+            // Lines starting with `%` are escaped from parsing.
+            '%' if text.len() == rest.len() || text[..text.len() - rest.len()].ends_with('\n') => {
+                rest = rest.split_once('\n').map_or("", |(_, rest)| rest);
+            }
+
+            '[' => {
+                let (tag, remainder) = split_tag(&rest[1..])?;
+                let (name, value) = tag
+                    .trim()
+                    .split_once(char::is_whitespace)
+                    .ok_or_else(|| PgnError::InvalidTag(tag.into()))?;
+                let value = value
+                    .trim()
+                    .strip_prefix('"')
+                    .and_then(|value| value.strip_suffix('"'))
+                    .ok_or_else(|| PgnError::InvalidTag(tag.into()))?;
+
+                let value = value.replace("\\\"", "\"").replace("\\\\", "\\");
+                tokens.push(Token::Tag(name.into(), value));
+                rest = remainder;
+            }
+
+            '{' => {
+                let (comment, remainder) = rest[1..]
+                    .split_once('}')
+                    .ok_or(PgnError::Unterminated("comment"))?;
+                let comment = comment.split_whitespace().collect::<Vec<_>>().join(" ");
+                tokens.push(Token::Comment(comment));
+                rest = remainder;
+            }
+
+            ';' => {
+                let (comment, remainder) = rest[1..].split_once('\n').unwrap_or((&rest[1..], ""));
+                tokens.push(Token::Comment(comment.trim().into()));
+                rest = remainder;
+            }
+
+            '(' => {
+                tokens.push(Token::VariationStart);
+                rest = &rest[1..];
+            }
+
+            ')' => {
+                tokens.push(Token::VariationEnd);
+                rest = &rest[1..];
+            }
+
+            _ => {
+                let end = rest
+                    .find(|c: char| c.is_whitespace() || "[]{}();".contains(c))
+                    .unwrap_or(rest.len())
+                    .max(c.len_utf8());
+                tokens.push(Token::Symbol(&rest[..end]));
+                rest = &rest[end..];
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Splits the contents of a tag from the text after its closing bracket, allowing for
+/// escaped quotes and brackets inside the value.
+fn split_tag(text: &str) -> Result<(&str, &str), PgnError> {
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for (i, c) in text.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            ']' if !in_string => return Ok((&text[..i], &text[i + 1..])),
+            _ => {}
+        }
+    }
+
+    Err(PgnError::Unterminated("tag"))
+}
@@ -38,6 +38,14 @@ impl PieceColor {
             PieceColor::Black => PieceColor::White,
         }
     }
+
+    /// Index for tables kept per colour, with White first.
+    pub fn index(self) -> usize {
+        match self {
+            PieceColor::White => 0,
+            PieceColor::Black => 1,
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
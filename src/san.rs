@@ -0,0 +1,213 @@
+use crate::{
+    board::FastBoard,
+    movement::{CastleDirection, Move, Promotion},
+    piece::PieceType,
+    position::BoardPosition,
+};
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum SanError {
+    #[error("Invalid move syntax `{0}`")]
+    InvalidSyntax(String),
+    #[error("No legal move matches `{0}`")]
+    NoMatchingMove(String),
+    #[error("`{0}` matches more than one legal move")]
+    AmbiguousMove(String),
+}
+
+impl FastBoard {
+    /// Formats a legal move in Standard Algebraic Notation, e.g. `Nbd2`, `exd5`,
+    /// `e8=Q+` or `O-O-O#`.
+    pub fn move_to_san(&mut self, board_move: Move) -> String {
+        let mut san = match board_move {
+            Move::Castle(CastleDirection::KingSide) => "O-O".to_string(),
+            Move::Castle(CastleDirection::QueenSide) => "O-O-O".to_string(),
+            Move::Direct {
+                from,
+                to,
+                promotion,
+            } => self.direct_move_to_san(from, to, promotion),
+        };
+
+        if self.make_move(board_move).is_ok() {
+            if self.is_in_check(self.next_to_move) {
+                san.push(match self.legal_moves().is_empty() {
+                    true => '#',
+                    false => '+',
+                });
+            }
+            self.unmake_last_move();
+        }
+
+        san
+    }
+
+    /// Parses a move in Standard Algebraic Notation, accepting it only if it names
+    /// exactly one legal move. Check and annotation suffixes are ignored.
+    pub fn parse_san(&self, san: &str) -> Result<Move, SanError> {
+        let syntax_error = || SanError::InvalidSyntax(san.into());
+        if !san.is_ascii() {
+            return Err(syntax_error());
+        }
+
+        let text = san.trim().trim_end_matches(['+', '#', '!', '?']);
+        let legal_moves = self.legal_moves();
+
+        let castle = match text {
+            "O-O" | "0-0" => Some(CastleDirection::KingSide),
+            "O-O-O" | "0-0-0" => Some(CastleDirection::QueenSide),
+            _ => None,
+        };
+        if let Some(direction) = castle {
+            return legal_moves
+                .into_iter()
+                .find(|&legal_move| legal_move == Move::Castle(direction))
+                .ok_or_else(|| SanError::NoMatchingMove(san.into()));
+        }
+
+        let (kind, text) = match text.chars().next().ok_or_else(syntax_error)? {
+            'K' => (PieceType::King, &text[1..]),
+            'Q' => (PieceType::Queen, &text[1..]),
+            'R' => (PieceType::Rook, &text[1..]),
+            'B' => (PieceType::Bishop, &text[1..]),
+            'N' => (PieceType::Knight, &text[1..]),
+            _ => (PieceType::Pawn, text),
+        };
+
+        // Promotions are written `e8=Q`, though `e8Q` is also common.
+        let (text, promotion) = match text.char_indices().last() {
+            Some((i, last)) if kind == PieceType::Pawn && last.is_ascii_uppercase() => {
+                let promotion = match last {
+                    'Q' => Promotion::Queen,
+                    'R' => Promotion::Rook,
+                    'B' => Promotion::Bishop,
+                    'N' => Promotion::Knight,
+                    _ => return Err(syntax_error()),
+                };
+                (text[..i].trim_end_matches('='), Some(promotion))
+            }
+            _ => (text, None),
+        };
+
+        let squares = text.replace('x', "");
+        let (disambiguation, destination) = squares
+            .len()
+            .checked_sub(2)
+            .map(|split| squares.split_at(split))
+            .ok_or_else(syntax_error)?;
+        let to = parse_square(destination).ok_or_else(syntax_error)?;
+
+        let (file, rank) = match disambiguation.as_bytes() {
+            [] => (None, None),
+            &[file @ b'a'..=b'h'] => (Some(file - b'a'), None),
+            &[rank @ b'1'..=b'8'] => (None, Some(rank - b'1')),
+            &[file @ b'a'..=b'h', rank @ b'1'..=b'8'] => (Some(file - b'a'), Some(rank - b'1')),
+            _ => return Err(syntax_error()),
+        };
+
+        let mut candidates = legal_moves.into_iter().filter(|&legal_move| {
+            let Move::Direct {
+                from,
+                to: legal_to,
+                promotion: legal_promotion,
+            } = legal_move
+            else {
+                return false;
+            };
+
+            self.mailbox.get(&from).map(|piece| piece.kind) == Some(kind)
+                && legal_to == to
+                && legal_promotion == promotion
+                && file.is_none_or(|file| from.file() == file)
+                && rank.is_none_or(|rank| from.rank() == rank)
+        });
+
+        match (candidates.next(), candidates.next()) {
+            (Some(board_move), None) => Ok(board_move),
+            (None, _) => Err(SanError::NoMatchingMove(san.into())),
+            (Some(_), Some(_)) => Err(SanError::AmbiguousMove(san.into())),
+        }
+    }
+
+    fn direct_move_to_san(
+        &mut self,
+        from: BoardPosition,
+        to: BoardPosition,
+        promotion: Option<Promotion>,
+    ) -> String {
+        let Some(piece) = self.mailbox.get(&from) else {
+            return format!("{}{}", from, to);
+        };
+        let captures = self.mailbox.get(&to).is_some()
+            || (piece.kind == PieceType::Pawn && from.file() != to.file());
+
+        let mut san = String::new();
+
+        if piece.kind == PieceType::Pawn {
+            if captures {
+                san.push((b'a' + from.file()) as char);
+            }
+        } else {
+            san.push(piece.to_char().to_ascii_uppercase());
+
+            // Other pieces of the same kind which could also move to the destination.
+            let rivals: Vec<_> = self
+                .legal_moves()
+                .into_iter()
+                .filter_map(|legal_move| match legal_move {
+                    Move::Direct {
+                        from: other,
+                        to: other_to,
+                        ..
+                    } if other != from
+                        && other_to == to
+                        && self.mailbox.get(&other) == Some(piece) =>
+                    {
+                        Some(other)
+                    }
+                    _ => None,
+                })
+                .collect();
+
+            if !rivals.is_empty() {
+                let file = (b'a' + from.file()) as char;
+                let rank = (b'1' + from.rank()) as char;
+
+                if rivals.iter().all(|other| other.file() != from.file()) {
+                    san.push(file);
+                } else if rivals.iter().all(|other| other.rank() != from.rank()) {
+                    san.push(rank);
+                } else {
+                    san.push(file);
+                    san.push(rank);
+                }
+            }
+        }
+
+        if captures {
+            san.push('x');
+        }
+        san.push_str(&to.to_string());
+
+        if let Some(promotion) = promotion {
+            san.push('=');
+            san.push(match promotion {
+                Promotion::Queen => 'Q',
+                Promotion::Rook => 'R',
+                Promotion::Bishop => 'B',
+                Promotion::Knight => 'N',
+            });
+        }
+
+        san
+    }
+}
+
+fn parse_square(text: &str) -> Option<BoardPosition> {
+    match text.as_bytes() {
+        &[file @ b'a'..=b'h', rank @ b'1'..=b'8'] => {
+            Some(BoardPosition::from_rank_file(rank - b'1', file - b'a'))
+        }
+        _ => None,
+    }
+}
@@ -1,45 +1,172 @@
 use std::{
+    fmt::Display,
     ops::Neg,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
     time::{Duration, Instant},
 };
 
 use crate::{
     board::{FastBoard, MoveError},
-    movement::Move,
+    eval,
+    movement::{Move, PackedMove, Promotion},
+    ordering::{MoveOrdering, MovePicker, OrderingStats, MAX_PLY},
+    pawns::PawnTable,
+    piece::PieceType,
     transposition::{Transposition, TranspositionTable},
 };
 
+/// Score for delivering checkmate at the root. Mate found further from the root
+/// scores one less per ply, so that faster mates are preferred.
+pub const MATE_VALUE: i32 = 1_000_000;
+
+/// Scores at least this large (in magnitude) are mate scores.
+const MATE_THRESHOLD: i32 = MATE_VALUE - MAX_PLY as i32;
+
+/// Safety margin for delta pruning in the quiescence search, in centipawns.
+const DELTA_MARGIN: i32 = 200;
+
 pub enum EndState {
     Checkmate,
     Stalemate,
     ThreeFoldRepetiiton,
+    FiftyMoveRule,
+    InsufficientMaterial,
+}
+
+/// Constraints on how long a call to [`MoveEngine::iterative_deepening`] may run.
+/// A search with no limits runs until the engine's stop flag is raised.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SearchLimits {
+    pub max_time: Option<Duration>,
+    pub max_depth: Option<u32>,
+}
+
+/// Outcome of a search to a fixed depth.
+#[derive(Debug, Clone)]
+pub struct SearchResult {
+    pub best_move: Move,
+    pub score: Score,
+    /// The expected line of play, starting with `best_move`.
+    pub pv: Vec<Move>,
+    pub depth: u32,
+    /// The deepest ply reached, including the quiescence search.
+    pub seldepth: usize,
+    pub nodes: u64,
+    pub time: Duration,
+    /// Occupancy of the transposition table, in permille.
+    pub hashfull: u32,
 }
 
 pub struct MoveEngine {
     transposition_table: TranspositionTable,
+    pawn_table: PawnTable,
+    ordering: MoveOrdering,
+    pv_table: PvTable,
+    /// The principal variation of the previous iteration, searched first in the next.
+    previous_pv: Vec<Move>,
+    follow_pv: bool,
+    stop: Arc<AtomicBool>,
+    nodes: u64,
+    seldepth: usize,
+}
+
+impl Default for MoveEngine {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl MoveEngine {
     pub fn new() -> Self {
         Self {
-            transposition_table: TranspositionTable::new(),
+            transposition_table: TranspositionTable::default(),
+            pawn_table: PawnTable::new(),
+            ordering: MoveOrdering::new(),
+            pv_table: PvTable::new(),
+            previous_pv: Vec::new(),
+            follow_pv: false,
+            stop: Arc::new(AtomicBool::new(false)),
+            nodes: 0,
+            seldepth: 0,
         }
     }
 
-    pub fn iterative_deepening(&mut self, board: &mut FastBoard, max_time: Duration) -> Move {
-        let expiry = Instant::now() + max_time;
+    /// Flag which aborts the current search when set. It is never cleared by the
+    /// engine itself, so callers should reset it before starting a new search.
+    pub fn stop_handle(&self) -> Arc<AtomicBool> {
+        self.stop.clone()
+    }
 
-        let mut best_move = None;
+    /// Searches successively deeper until a limit is reached, calling `on_iteration`
+    /// after each completed depth. Returns `None` only if there are no legal moves.
+    pub fn iterative_deepening(
+        &mut self,
+        board: &mut FastBoard,
+        limits: SearchLimits,
+        mut on_iteration: impl FnMut(&SearchResult),
+    ) -> Option<SearchResult> {
+        let start = Instant::now();
+        let expiry = limits.max_time.map(|max_time| start + max_time);
+        self.nodes = 0;
+        self.ordering.new_search();
+        self.transposition_table.new_search();
+        self.previous_pv.clear();
+
+        // The PV table has a row per ply, which bounds how deep we can search.
+        let max_depth = limits.max_depth.unwrap_or(u32::MAX).min(MAX_PLY as u32 - 1);
+
+        let mut best_result = None;
         let mut depth = 0;
-        loop {
-            println!("Searching depth {}...", depth);
-            let Some(depth_best_move) = self.find_best_move(board, depth, expiry) else {
-                return best_move.unwrap();
+        while depth < max_depth {
+            let Some(mut result) = self.find_best_move(board, depth, expiry) else {
+                break;
             };
 
-            best_move = Some(depth_best_move);
+            // Report depths in plies, counting the root move.
+            result.depth = depth + 1;
+            result.time = start.elapsed();
+            result.hashfull = self.transposition_table.hashfull();
+            on_iteration(&result);
+
+            self.previous_pv = result.pv.clone();
+            best_result = Some(result);
             depth += 1;
         }
+
+        // If the search was cut off before finishing its first iteration, fall back to
+        // any legal move so that the caller always has something to play.
+        best_result.or_else(|| {
+            let fallback = board.legal_moves().first().copied()?;
+            Some(SearchResult {
+                best_move: fallback,
+                score: Score::exact(board.evaluate_cached(&mut self.pawn_table)),
+                pv: vec![fallback],
+                depth: 0,
+                seldepth: 0,
+                nodes: self.nodes,
+                time: start.elapsed(),
+                hashfull: self.transposition_table.hashfull(),
+            })
+        })
+    }
+
+    /// Resets everything learned from previous searches, ready for an unrelated game.
+    pub fn new_game(&mut self) {
+        self.transposition_table.clear();
+        self.ordering = MoveOrdering::new();
+    }
+
+    /// Replaces the transposition table with an empty one of the given size.
+    pub fn resize_hash(&mut self, megabytes: usize) {
+        self.transposition_table = TranspositionTable::new(megabytes);
+    }
+
+    /// Move ordering statistics for the most recent search.
+    pub fn ordering_stats(&self) -> &OrderingStats {
+        self.ordering.stats()
     }
 
     pub fn get_end_state(&mut self, board: &mut FastBoard) -> Option<EndState> {
@@ -47,24 +174,14 @@ impl MoveEngine {
             return Some(EndState::ThreeFoldRepetiiton);
         }
 
-        let possible_moves = board.calculate_pseudo_moves();
-        let mut able_to_move = false;
-
-        for possible_move in possible_moves {
-            let movement = board.make_move(possible_move);
-            if let Err(MoveError::IllegalMove) = movement {
-                continue;
-            }
-
-            // println!("{}", possible_move);
-
-            movement.unwrap();
-            able_to_move = true;
-            board.unmake_last_move();
+        if board.has_insufficient_material() {
+            return Some(EndState::InsufficientMaterial);
         }
 
-        if able_to_move {
-            None
+        if !board.legal_moves().is_empty() {
+            board
+                .is_fifty_move_draw()
+                .then_some(EndState::FiftyMoveRule)
         } else if board.is_in_check(board.next_to_move) {
             Some(EndState::Checkmate)
         } else {
@@ -76,20 +193,27 @@ impl MoveEngine {
         &mut self,
         board: &mut FastBoard,
         depth: u32,
-        expiry: Instant,
-    ) -> Option<Move> {
-        let possible_moves = board.calculate_pseudo_moves();
+        expiry: Option<Instant>,
+    ) -> Option<SearchResult> {
+        let start = Instant::now();
+        self.seldepth = 0;
+        self.pv_table.clear(0);
+
+        // Search the previous principal variation first, so that its moves are tried
+        // first (and its transpositions are filled in) throughout the tree.
+        let mut possible_moves = board.legal_moves();
+        let pv_move = self.previous_pv.first().copied();
+        let pv_index = possible_moves.iter().position(|m| Some(*m) == pv_move);
+        if let Some(index) = pv_index {
+            possible_moves[..=index].rotate_right(1);
+        }
+        self.follow_pv = pv_index.is_some();
 
         let mut best_move = None;
         let mut best_score = Score::lowest();
 
         for possible_move in possible_moves {
-            let movement = board.make_move(possible_move);
-            if let Err(MoveError::IllegalMove) = movement {
-                continue;
-            }
-
-            movement.unwrap();
+            board.make_move(possible_move).unwrap();
 
             if best_move.is_none() {
                 best_move = Some(possible_move);
@@ -98,6 +222,7 @@ impl MoveEngine {
             let Some(score) = self.alpha_beta(
                 board,
                 depth,
+                1,
                 Score::initial_alpha(),
                 Score::initial_beta(),
                 expiry,
@@ -107,36 +232,69 @@ impl MoveEngine {
             };
             // println!("{}", possible_move);
             let score = -score;
+            self.follow_pv = false;
 
-            if score > best_score {
+            if score > best_score || best_move == Some(possible_move) {
                 best_move = Some(possible_move);
                 best_score = score;
+                self.pv_table.update(0, possible_move);
             }
 
             board.unmake_last_move();
         }
 
-        Some(best_move.unwrap())
+        Some(SearchResult {
+            best_move: best_move?,
+            score: best_score,
+            pv: self.pv_table.line(0).to_vec(),
+            depth,
+            seldepth: self.seldepth,
+            nodes: self.nodes,
+            time: start.elapsed(),
+            hashfull: self.transposition_table.hashfull(),
+        })
     }
 
     pub fn alpha_beta(
         &mut self,
         board: &mut FastBoard,
         depth: u32,
+        ply: usize,
         mut alpha: Score,
         beta: Score,
-        expiry: Instant,
+        expiry: Option<Instant>,
     ) -> Option<Score> {
-        if Instant::now() > expiry {
+        if self.should_stop(expiry) {
             return None;
         }
 
-        if board.get_repetitions() == 3 {
+        self.nodes += 1;
+        self.seldepth = self.seldepth.max(ply);
+        self.pv_table.clear(ply);
+
+        if board.get_repetitions() == 3 || board.has_insufficient_material() {
             return Some(Score::exact(0));
         }
+        if let Some(score) = fifty_move_score(board, ply) {
+            return Some(score);
+        }
+
+        // Mate distance pruning: no line from here can do better than mating on the next
+        // move or worse than being mated now, so stop if that cannot change the result.
+        let mate_ceiling = Score::mate_in(ply + 1);
+        if mate_ceiling <= alpha {
+            return Some(mate_ceiling.make_upper_bound());
+        }
+
+        let mate_floor = Score::mated_in(ply);
+        if mate_floor >= beta.make_exact() {
+            return Some(mate_floor.make_lower_bound());
+        }
 
+        let mut tt_move = None;
         if let Some(transposition) = self.transposition_table.get(&board.zobrist_key) {
-            let score = transposition.score;
+            tt_move = transposition.best_move.map(Move::from);
+            let score = transposition.score(ply);
             if transposition.depth >= depth {
                 let should_use = match score.bound() {
                     ScoreBound::Exact => true,
@@ -153,63 +311,234 @@ impl MoveEngine {
         if depth == 0 {
             // print_board(board);
             // dbg!(board.evaluate());
-            return Some(self.quiesce(board, alpha, beta));
+            return Some(self.quiesce(board, ply, alpha, beta));
         }
 
         let cut = beta.make_exact();
 
         let mut best_score = Score::lowest();
-        let mut able_to_move = false;
-
-        let possible_moves = board.calculate_pseudo_moves();
-        for possible_move in possible_moves {
-            let movement = board.make_move(possible_move);
-
-            if let Err(MoveError::IllegalMove) = movement {
-                continue;
-            }
-
-            movement.unwrap();
-
-            able_to_move = true;
-
-            let Some(score) = self.alpha_beta(board, depth - 1, -beta, -alpha, expiry) else {
+        let mut best_move = None;
+        let mut moves_searched = 0;
+
+        let possible_moves = board.legal_moves();
+
+        // While still on the previous principal variation, its move takes priority.
+        let pv_move = self
+            .previous_pv
+            .get(ply)
+            .copied()
+            .filter(|pv_move| self.follow_pv && possible_moves.contains(pv_move));
+        self.follow_pv = pv_move.is_some();
+
+        let hash_move = pv_move.or(tt_move);
+        let move_picker = MovePicker::new(board, possible_moves, hash_move, &self.ordering, ply);
+        for possible_move in move_picker {
+            board.make_move(possible_move).unwrap();
+            moves_searched += 1;
+
+            let Some(score) = self.alpha_beta(board, depth - 1, ply + 1, -beta, -alpha, expiry)
+            else {
                 board.unmake_last_move();
                 return None;
             };
             let score = -score;
-            best_score = best_score.max(score);
+            self.follow_pv = false;
+
+            if best_move.is_none() || score > best_score {
+                best_score = score;
+                best_move = Some(possible_move);
+                self.pv_table.update(ply, possible_move);
+            }
 
             board.unmake_last_move();
             alpha = alpha.max(score.make_upper_bound());
 
             if score >= cut {
                 best_score = score.make_lower_bound();
+                self.ordering
+                    .record_cutoff(board, possible_move, depth, ply, moves_searched == 1);
                 break;
             }
         }
 
-        let transposition = Transposition::new(best_score, depth);
+        if moves_searched == 0 {
+            best_score = if board.is_in_check(board.next_to_move) {
+                Score::mated_in(ply)
+            } else {
+                Score::exact(0)
+            };
+        }
+
+        let best_move = best_move.map(|best_move| PackedMove::new(board, best_move));
+        let transposition = Transposition::new(best_score, depth, best_move, ply);
         self.transposition_table
             .set(board.zobrist_key, transposition);
 
-        Some(if able_to_move {
-            best_score
-        } else if board.is_in_check(board.next_to_move) {
-            Score::lowest()
+        Some(best_score)
+    }
+
+    /// Searches captures and promotions until the position is quiet, so that positions
+    /// are never evaluated in the middle of an exchange.
+    pub fn quiesce(
+        &mut self,
+        board: &mut FastBoard,
+        ply: usize,
+        mut alpha: Score,
+        beta: Score,
+    ) -> Score {
+        self.seldepth = self.seldepth.max(ply);
+
+        if board.get_repetitions() == 3 {
+            return Score::exact(0);
+        }
+        if let Some(score) = fifty_move_score(board, ply) {
+            return score;
+        }
+
+        let cut = beta.make_exact();
+
+        // Standing pat is not an option when in check, so every evasion is searched.
+        let in_check = board.is_in_check(board.next_to_move);
+
+        let stand_pat = Score::exact(board.evaluate_cached(&mut self.pawn_table));
+        let mut best_score = Score::mated_in(ply);
+        let possible_moves = if in_check {
+            board.legal_moves()
         } else {
-            Score::exact(0)
-        })
+            // The side to move can usually do at least as well as the static evaluation
+            // by declining every capture.
+            if stand_pat >= cut {
+                return stand_pat.make_lower_bound();
+            }
+
+            alpha = alpha.max(stand_pat);
+            best_score = stand_pat;
+            board.calculate_pseudo_captures()
+        };
+
+        // Searching the most valuable captures first keeps the tree small.
+        let move_picker = MovePicker::new(board, possible_moves, None, &self.ordering, ply);
+        for possible_move in move_picker {
+            if !in_check {
+                // Underpromotions are almost never better than promoting to a queen.
+                if let Move::Direct {
+                    promotion: Some(promotion),
+                    ..
+                } = possible_move
+                {
+                    if promotion != Promotion::Queen {
+                        continue;
+                    }
+                }
+
+                // Delta pruning: skip captures which cannot raise alpha even with a
+                // generous positional bonus.
+                let gain = material_gain(board, possible_move);
+                if Score::exact(stand_pat.value() + gain + DELTA_MARGIN) < alpha {
+                    continue;
+                }
+            }
+
+            let movement = board.make_move(possible_move);
+            if let Err(MoveError::IllegalMove) = movement {
+                continue;
+            }
+
+            movement.unwrap();
+            self.nodes += 1;
+
+            let score = -self.quiesce(board, ply + 1, -beta, -alpha);
+            board.unmake_last_move();
+
+            best_score = best_score.max(score);
+            alpha = alpha.max(score.make_upper_bound());
+
+            if score >= cut {
+                return score.make_lower_bound();
+            }
+        }
+
+        // If no evasion was legal, this is checkmate.
+        best_score
+    }
+
+    fn should_stop(&self, expiry: Option<Instant>) -> bool {
+        self.stop.load(Ordering::Relaxed) || expiry.is_some_and(|expiry| Instant::now() > expiry)
+    }
+}
+
+/// Triangular table of principal variations, where row `ply` holds the best line
+/// found so far from the node being searched at that ply.
+struct PvTable {
+    lines: Vec<Vec<Move>>,
+}
+
+impl PvTable {
+    fn new() -> Self {
+        Self {
+            lines: vec![Vec::new(); MAX_PLY],
+        }
+    }
+
+    fn clear(&mut self, ply: usize) {
+        self.lines[ply].clear();
+    }
+
+    /// Makes `best_move` followed by the child's line the best line at `ply`.
+    fn update(&mut self, ply: usize, best_move: Move) {
+        let (lines, child_lines) = self.lines.split_at_mut(ply + 1);
+        let line = &mut lines[ply];
+
+        line.clear();
+        line.push(best_move);
+        line.extend_from_slice(&child_lines[0]);
+    }
+
+    fn line(&self, ply: usize) -> &[Move] {
+        &self.lines[ply]
+    }
+}
+
+/// Material won by a capture or promotion, using the evaluation's piece values.
+fn material_gain(board: &FastBoard, board_move: Move) -> i32 {
+    let Move::Direct {
+        from,
+        to,
+        promotion,
+    } = board_move
+    else {
+        return 0;
+    };
+
+    let piece_values = &eval::params().piece_values;
+    let value = |kind| piece_values[eval::piece_index(kind)];
+
+    let captured = match board.mailbox.get(&to) {
+        Some(piece) => value(piece.kind),
+        // A pawn moving diagonally onto an empty square is capturing en passant.
+        None if from.file() != to.file() => value(PieceType::Pawn),
+        None => 0,
+    };
+
+    let promoted = promotion.map_or(0, |promotion| {
+        value(promotion.piece_type()) - value(PieceType::Pawn)
+    });
+
+    captured + promoted
+}
+
+/// The score of a position ended by the fifty-move rule. When in check the position could be
+/// checkmate, which takes precedence, as in [`MoveEngine::get_end_state`]. Any other
+/// position is drawn, even if every evasion would reset the clock.
+fn fifty_move_score(board: &FastBoard, ply: usize) -> Option<Score> {
+    if !board.is_fifty_move_draw() {
+        return None;
     }
 
-    pub fn quiesce(&mut self, board: &mut FastBoard, _alpha: Score, _beta: Score) -> Score {
-        // match self.get_end_state(board) {
-        //     Some(EndState::Checkmate) => Score::lowest(),
-        //     Some(EndState::Stalemate) => Score::exact(0),
-        //     Some(EndState::ThreeFoldRepetiiton) => Score::exact(0),
-        //     None => Score::exact(board.evaluate()),
-        // }
-        Score::exact(board.evaluate())
+    if board.is_in_check(board.next_to_move) && board.legal_moves().is_empty() {
+        Some(Score::mated_in(ply))
+    } else {
+        Some(Score::exact(0))
     }
 }
 
@@ -241,6 +570,32 @@ impl Score {
         Self(4 * value)
     }
 
+    /// The score for the side to move delivering checkmate `ply` moves from the root.
+    pub fn mate_in(ply: usize) -> Self {
+        Self::exact(MATE_VALUE - ply as i32)
+    }
+
+    /// The score for the side to move being checkmated `ply` moves from the root.
+    pub fn mated_in(ply: usize) -> Self {
+        -Self::mate_in(ply)
+    }
+
+    pub fn is_mate(&self) -> bool {
+        (MATE_THRESHOLD..=MATE_VALUE).contains(&self.value().abs())
+    }
+
+    /// The number of moves until mate, negative if the side to move is being mated.
+    pub fn mate_in_moves(&self) -> Option<i32> {
+        if !self.is_mate() {
+            return None;
+        }
+
+        let plies = MATE_VALUE - self.value().abs();
+        let moves = (plies + 1) / 2;
+
+        Some(moves * self.value().signum())
+    }
+
     pub fn make_exact(self) -> Self {
         Self((self.0 + 1) & !3)
     }
@@ -253,6 +608,30 @@ impl Score {
         Self(self.make_exact().0 - 1)
     }
 
+    /// The score in centipawns, discarding any bound information.
+    pub fn value(&self) -> i32 {
+        self.make_exact().0 / 4
+    }
+
+    /// Converts a mate score from distance to mate from the root into distance to mate
+    /// from a node `ply` moves from the root.
+    pub fn to_relative(self, ply: usize) -> Self {
+        if !self.is_mate() {
+            return self;
+        }
+
+        Self(self.0 + 4 * self.value().signum() * ply as i32)
+    }
+
+    /// The inverse of [`Score::to_relative`].
+    pub fn from_relative(self, ply: usize) -> Self {
+        if !self.is_mate() {
+            return self;
+        }
+
+        Self(self.0 - 4 * self.value().signum() * ply as i32)
+    }
+
     pub fn bound(&self) -> ScoreBound {
         match self.0 & 3 {
             0 => ScoreBound::Exact,
@@ -263,6 +642,17 @@ impl Score {
     }
 }
 
+/// Formats the score as in UCI, e.g. `cp 35` or `mate -2`.
+impl Display for Score {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.mate_in_moves() {
+            Some(moves) => write!(f, "mate {}", moves),
+            None => write!(f, "cp {}", self.value()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ScoreBound {
     UpperBound,
     Exact,
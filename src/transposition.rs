@@ -1,13 +1,14 @@
-use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
 
 use lazy_static::lazy_static;
 use rand::random;
 
 use crate::{
     castle::CastlingRights,
+    movement::PackedMove,
     piece::{Piece, PieceColor, PieceType},
     position::BoardPosition,
-    search::Score,
+    search::{Score, ScoreBound},
 };
 
 lazy_static! {
@@ -29,36 +30,207 @@ lazy_static! {
     static ref BLACK_TO_MOVE: u64 = random();
 }
 
+/// Size of the transposition table if none is given, in megabytes.
+pub const DEFAULT_HASH_MB: usize = 16;
+
+/// Generations wrap around at this mask, to fit in the bits left in an entry.
+const GENERATION_MASK: u8 = 0x3f;
+
 #[derive(Debug, Clone)]
 pub struct Transposition {
+    /// The exact value of the score, with mate distances counted from this position
+    /// rather than the root.
     pub score: Score,
+    pub bound: ScoreBound,
     pub depth: u32,
+    pub best_move: Option<PackedMove>,
 }
 
 impl Transposition {
-    pub fn new(score: Score, depth: u32) -> Self {
-        Self { score, depth }
+    /// Records the result of a search `ply` moves from the root.
+    pub fn new(score: Score, depth: u32, best_move: Option<PackedMove>, ply: usize) -> Self {
+        Self {
+            score: score.make_exact().to_relative(ply),
+            bound: score.bound(),
+            depth,
+            best_move,
+        }
+    }
+
+    /// The stored score, with its bound, as seen from a node `ply` moves from the root.
+    pub fn score(&self, ply: usize) -> Score {
+        let score = self.score.from_relative(ply);
+
+        match self.bound {
+            ScoreBound::Exact => score,
+            ScoreBound::LowerBound => score.make_lower_bound(),
+            ScoreBound::UpperBound => score.make_upper_bound(),
+        }
     }
 }
 
-#[derive(Debug, Clone)]
+/// A preallocated hash table of search results, made of two-entry buckets: the first
+/// entry keeps the deepest recent search of a position, the second always takes the
+/// newest one.
+///
+/// Entries are stored locklessly as a pair of words, with the key XORed with the data
+/// so that an entry torn by a concurrent write fails verification instead of being
+/// misread.
+#[derive(Debug)]
 pub struct TranspositionTable {
-    table: HashMap<ZobristKey, Transposition>,
+    entries: Box<[Entry]>,
+    bucket_mask: usize,
+    generation: u8,
+}
+
+#[derive(Debug, Default)]
+struct Entry {
+    key: AtomicU64,
+    data: AtomicU64,
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        Self::new(DEFAULT_HASH_MB)
+    }
 }
 
 impl TranspositionTable {
-    pub fn new() -> Self {
+    /// Allocates a table of (at most) the given size in megabytes.
+    pub fn new(megabytes: usize) -> Self {
+        let bucket_size = 2 * size_of::<Entry>();
+        // Round down to a power of two, so that a key can be masked into a bucket index.
+        let buckets = 1 << (megabytes.max(1) * 1024 * 1024 / bucket_size).ilog2();
+
         Self {
-            table: HashMap::new(),
+            entries: (0..buckets * 2).map(|_| Entry::default()).collect(),
+            bucket_mask: buckets - 1,
+            generation: 0,
+        }
+    }
+
+    /// Marks the start of a new search, so that entries from earlier searches are
+    /// preferred for replacement.
+    pub fn new_search(&mut self) {
+        self.generation = (self.generation + 1) & GENERATION_MASK;
+    }
+
+    pub fn clear(&mut self) {
+        for entry in self.entries.iter_mut() {
+            *entry = Entry::default();
+        }
+        self.generation = 0;
+    }
+
+    pub fn get(&self, zobrist_key: &ZobristKey) -> Option<Transposition> {
+        let bucket = self.bucket(zobrist_key);
+
+        bucket.iter().find_map(|entry| {
+            let data = entry.data.load(Ordering::Relaxed);
+            let key = entry.key.load(Ordering::Relaxed);
+
+            (data != 0 && key ^ data == zobrist_key.0).then(|| EntryData::unpack(data).into())
+        })
+    }
+
+    pub fn set(&self, zobrist_key: ZobristKey, transposition: Transposition) {
+        let bucket = self.bucket(&zobrist_key);
+        let [depth_preferred, always_replace] = bucket else {
+            unreachable!();
+        };
+
+        let data = EntryData {
+            transposition,
+            generation: self.generation,
         }
+        .pack();
+
+        // Overwrite the depth-preferred entry if it holds the same position, a
+        // shallower search, or a search from an earlier move.
+        let existing_data = depth_preferred.data.load(Ordering::Relaxed);
+        let existing_key = depth_preferred.key.load(Ordering::Relaxed) ^ existing_data;
+        let existing = EntryData::unpack(existing_data);
+
+        let entry = if existing_data == 0
+            || existing_key == zobrist_key.0
+            || existing.generation != self.generation
+            || existing.transposition.depth <= EntryData::unpack(data).transposition.depth
+        {
+            depth_preferred
+        } else {
+            always_replace
+        };
+
+        entry.key.store(zobrist_key.0 ^ data, Ordering::Relaxed);
+        entry.data.store(data, Ordering::Relaxed);
+    }
+
+    /// Approximate occupancy by entries from the current search, in permille.
+    pub fn hashfull(&self) -> u32 {
+        let sample = &self.entries[..self.entries.len().min(1000)];
+        let used = sample
+            .iter()
+            .filter(|entry| {
+                let data = entry.data.load(Ordering::Relaxed);
+                data != 0 && EntryData::unpack(data).generation == self.generation
+            })
+            .count();
+
+        (used * 1000 / sample.len()) as u32
+    }
+
+    fn bucket(&self, zobrist_key: &ZobristKey) -> &[Entry] {
+        let index = (zobrist_key.0 as usize & self.bucket_mask) * 2;
+        &self.entries[index..index + 2]
+    }
+}
+
+/// The contents of an entry, packed into a single word as:
+/// best move (16 bits) | score (32 bits) | depth (8 bits) | bound (2 bits) |
+/// generation (6 bits).
+struct EntryData {
+    transposition: Transposition,
+    generation: u8,
+}
+
+impl EntryData {
+    fn pack(&self) -> u64 {
+        let best_move = self
+            .transposition
+            .best_move
+            .map_or(0, |packed| packed.bits()) as u64;
+        let score = self.transposition.score.value() as u32 as u64;
+        let depth = self.transposition.depth.min(u8::MAX as u32) as u64;
+        let bound = match self.transposition.bound {
+            ScoreBound::Exact => 0,
+            ScoreBound::LowerBound => 1,
+            ScoreBound::UpperBound => 2,
+        };
+        let generation = self.generation as u64;
+
+        best_move | score << 16 | depth << 48 | bound << 56 | generation << 58
     }
 
-    pub fn get(&self, zobrist_key: &ZobristKey) -> Option<&Transposition> {
-        self.table.get(zobrist_key)
+    fn unpack(data: u64) -> Self {
+        Self {
+            transposition: Transposition {
+                best_move: PackedMove::from_bits(data as u16),
+                score: Score::exact((data >> 16) as u32 as i32),
+                bound: match data >> 56 & 3 {
+                    0 => ScoreBound::Exact,
+                    1 => ScoreBound::LowerBound,
+                    _ => ScoreBound::UpperBound,
+                },
+                depth: (data >> 48) as u8 as u32,
+            },
+            generation: (data >> 58) as u8,
+        }
     }
+}
 
-    pub fn set(&mut self, zobrist_key: ZobristKey, transposition: Transposition) {
-        self.table.insert(zobrist_key, transposition);
+impl From<EntryData> for Transposition {
+    fn from(data: EntryData) -> Self {
+        data.transposition
     }
 }
 
@@ -68,6 +240,17 @@ impl ZobristKey {
     pub(crate) fn new() -> Self {
         Self(0)
     }
+
+    /// The key of an empty board with the given castling rights.
+    pub(crate) fn with_castling_rights(rights: &CastlingRights) -> Self {
+        let mut key = Self::new();
+        key.toggle_castling_rights(rights);
+        key
+    }
+
+    pub(crate) fn inner(&self) -> u64 {
+        self.0
+    }
 }
 
 impl ZobristKey {
@@ -98,8 +281,8 @@ impl ZobristKey {
     }
 
     pub fn toggle_castling_rights(&mut self, rights: &CastlingRights) {
-        let rights_index = ((rights.white.queenside as usize) << 3)
-            + ((rights.white.kingside as usize) << 2)
+        let rights_index = ((rights.black.queenside as usize) << 3)
+            + ((rights.black.kingside as usize) << 2)
             + ((rights.white.queenside as usize) << 1)
             + rights.white.kingside as usize;
 
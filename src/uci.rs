@@ -0,0 +1,384 @@
+use std::{
+    fmt::Display,
+    io::{self, BufRead, Write},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use anyhow::{anyhow, bail};
+
+use crate::{
+    board::FastBoard,
+    movement::{CastleDirection, Move, Promotion},
+    piece::{PieceColor, PieceType},
+    position::BoardPosition,
+    search::{MoveEngine, SearchLimits, SearchResult},
+    transposition::DEFAULT_HASH_MB,
+};
+
+/// Largest transposition table accepted through the `Hash` option, in megabytes.
+const MAX_HASH_MB: usize = 4096;
+
+/// Time kept in reserve so that the engine never flags while communicating its move.
+const MOVE_OVERHEAD: Duration = Duration::from_millis(50);
+
+/// Runs the engine as a UCI client, reading commands from `input` and writing
+/// responses to `output` until `quit` is received or the input is closed.
+///
+/// `quit` interrupts a search in progress, while closing the input lets it finish and
+/// report its move first (except for `go infinite`, which would never end).
+pub fn run(input: impl BufRead, output: impl Write + Send + 'static) -> anyhow::Result<()> {
+    let mut session = UciSession::new(output);
+
+    for line in input.lines() {
+        let line = line?;
+        match session.handle_command(&line) {
+            Ok(true) => {}
+            Ok(false) => {
+                session.stop_search();
+                return Ok(());
+            }
+            Err(error) => session
+                .output
+                .send(format!("info string error: {}", error))?,
+        }
+    }
+
+    session.finish_search();
+
+    Ok(())
+}
+
+/// The engine's side of the protocol, shared with the search thread.
+struct Output<W>(Arc<Mutex<W>>);
+
+impl<W: Write> Output<W> {
+    fn send(&self, line: impl Display) -> io::Result<()> {
+        let mut output = self.0.lock().expect("output lock poisoned");
+        writeln!(output, "{}", line)?;
+        output.flush()
+    }
+}
+
+impl<W> Clone for Output<W> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+struct UciSession<W> {
+    board: FastBoard,
+    engine: Option<MoveEngine>,
+    search: Option<Search>,
+    stop: Arc<AtomicBool>,
+    output: Output<W>,
+}
+
+struct Search {
+    thread: JoinHandle<MoveEngine>,
+    infinite: bool,
+}
+
+impl<W: Write + Send + 'static> UciSession<W> {
+    fn new(output: W) -> Self {
+        let engine = MoveEngine::new();
+        let stop = engine.stop_handle();
+
+        Self {
+            board: FastBoard::initial(),
+            engine: Some(engine),
+            search: None,
+            stop,
+            output: Output(Arc::new(Mutex::new(output))),
+        }
+    }
+
+    /// Handles a single line of input, returning `false` if the session should end.
+    fn handle_command(&mut self, line: &str) -> anyhow::Result<bool> {
+        let mut words = line.split_whitespace();
+
+        match words.next() {
+            Some("uci") => {
+                self.output.send("id name Rusty Chess Bot")?;
+                self.output.send("id author benstockil")?;
+                self.output.send(format!(
+                    "option name Hash type spin default {} min 1 max {}",
+                    DEFAULT_HASH_MB, MAX_HASH_MB
+                ))?;
+                self.output.send("uciok")?;
+            }
+            Some("isready") => self.output.send("readyok")?,
+            Some("ucinewgame") => {
+                self.stop_search();
+                self.engine().new_game();
+                self.board = FastBoard::initial();
+            }
+            Some("setoption") => {
+                self.stop_search();
+                self.set_option(words)?;
+            }
+            Some("position") => {
+                self.stop_search();
+                self.board = parse_position(words)?;
+            }
+            Some("go") => {
+                self.stop_search();
+                self.go(words)?;
+            }
+            Some("stop") => self.stop_search(),
+            Some("quit") => return Ok(false),
+            // Unknown commands are ignored, as required by the protocol.
+            _ => {}
+        }
+
+        Ok(true)
+    }
+
+    fn engine(&mut self) -> &mut MoveEngine {
+        self.engine
+            .as_mut()
+            .expect("engine is only taken while a search is running")
+    }
+
+    fn set_option<'a>(&mut self, mut words: impl Iterator<Item = &'a str>) -> anyhow::Result<()> {
+        if words.next() != Some("name") {
+            bail!("expected `name`");
+        }
+
+        let name: Vec<_> = words.by_ref().take_while(|word| *word != "value").collect();
+        let name = name.join(" ");
+
+        if name.eq_ignore_ascii_case("Hash") {
+            let megabytes: usize = parse_value(words.next())?;
+            self.engine().resize_hash(megabytes.clamp(1, MAX_HASH_MB));
+        }
+
+        // Unknown options are ignored.
+        Ok(())
+    }
+
+    fn go<'a>(&mut self, mut words: impl Iterator<Item = &'a str>) -> anyhow::Result<()> {
+        let mut limits = SearchLimits::default();
+        let mut time_left = None;
+        let mut increment = Duration::ZERO;
+        let mut infinite = false;
+
+        let (time_key, increment_key) = match self.board.next_to_move {
+            PieceColor::White => ("wtime", "winc"),
+            PieceColor::Black => ("btime", "binc"),
+        };
+
+        while let Some(word) = words.next() {
+            match word {
+                "infinite" => infinite = true,
+                "depth" => limits.max_depth = Some(parse_value(words.next())?),
+                "movetime" => {
+                    limits.max_time = Some(Duration::from_millis(parse_value(words.next())?))
+                }
+                key if key == time_key => {
+                    time_left = Some(Duration::from_millis(parse_value(words.next())?))
+                }
+                key if key == increment_key => {
+                    increment = Duration::from_millis(parse_value(words.next())?)
+                }
+                // Skip the value of any parameter we do not use (e.g. the opponent's clock).
+                "wtime" | "btime" | "winc" | "binc" | "movestogo" | "nodes" | "mate" => {
+                    words.next();
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(time_left) = time_left {
+            let budget = time_left / 30 + increment / 2;
+            let max_time = budget.min(time_left.saturating_sub(MOVE_OVERHEAD));
+            limits.max_time = Some(limits.max_time.map_or(max_time, |t| t.min(max_time)));
+        }
+
+        let mut board = self.board.clone();
+        let mut engine = self.engine.take().unwrap();
+        let stop = self.stop.clone();
+        let output = self.output.clone();
+        stop.store(false, Ordering::Relaxed);
+
+        // Write errors mean the GUI has gone away, so there is no one left to tell.
+        let thread = thread::spawn(move || {
+            let root = board.clone();
+            let result = engine.iterative_deepening(&mut board, limits, |result| {
+                let _ = output.send(format_info(&root, result));
+            });
+
+            // An infinite search may only report its move once told to stop, even if
+            // it runs out of depth first.
+            while infinite && !stop.load(Ordering::Relaxed) {
+                thread::park();
+            }
+
+            let _ = match result {
+                Some(result) => output.send(format!(
+                    "bestmove {}",
+                    result.best_move.to_uci(root.next_to_move)
+                )),
+                None => output.send("bestmove 0000"),
+            };
+
+            engine
+        });
+
+        self.search = Some(Search { thread, infinite });
+
+        Ok(())
+    }
+
+    /// Interrupts any search in progress and waits for it to report its move.
+    fn stop_search(&mut self) {
+        if let Some(search) = self.search.take() {
+            self.stop.store(true, Ordering::Relaxed);
+            search.thread.thread().unpark();
+            self.engine = Some(search.thread.join().expect("search thread panicked"));
+        }
+    }
+
+    /// Waits for a search in progress to reach its limits and report its move. An
+    /// infinite search is stopped, as nothing else could end it.
+    fn finish_search(&mut self) {
+        match &self.search {
+            Some(search) if !search.infinite => {
+                let search = self.search.take().unwrap();
+                self.engine = Some(search.thread.join().expect("search thread panicked"));
+            }
+            _ => self.stop_search(),
+        }
+    }
+}
+
+fn format_info(board: &FastBoard, result: &SearchResult) -> String {
+    let millis = result.time.as_millis();
+    let nps = result.nodes as u128 * 1000 / millis.max(1);
+
+    format!(
+        "info depth {} seldepth {} score {} nodes {} nps {} hashfull {} time {} pv {}",
+        result.depth,
+        result.seldepth,
+        result.score,
+        result.nodes,
+        nps,
+        result.hashfull,
+        millis,
+        format_pv(board, &result.pv),
+    )
+}
+
+/// Formats a line of play, tracking the side to move so castling is written correctly.
+fn format_pv(board: &FastBoard, pv: &[Move]) -> String {
+    let mut board = board.clone();
+    let mut moves = Vec::with_capacity(pv.len());
+
+    for &pv_move in pv {
+        moves.push(pv_move.to_uci(board.next_to_move));
+        if board.make_move(pv_move).is_err() {
+            break;
+        }
+    }
+
+    moves.join(" ")
+}
+
+fn parse_value<T: std::str::FromStr>(word: Option<&str>) -> anyhow::Result<T> {
+    let word = word.ok_or_else(|| anyhow!("missing parameter value"))?;
+    word.parse()
+        .map_err(|_| anyhow!("invalid parameter value `{}`", word))
+}
+
+fn parse_position<'a>(mut words: impl Iterator<Item = &'a str>) -> anyhow::Result<FastBoard> {
+    let mut board = match words.next() {
+        Some("startpos") => FastBoard::initial(),
+        Some("fen") => {
+            let fen: Vec<_> = words.by_ref().take_while(|word| *word != "moves").collect();
+            let mut board = FastBoard::from_fen(&fen.join(" "))?;
+            board.repetitions.insert(board.zobrist_key, 1);
+            board
+        }
+        _ => bail!("expected `startpos` or `fen`"),
+    };
+
+    for word in words.skip_while(|word| *word == "moves") {
+        let board_move = board.parse_uci_move(word)?;
+        board.make_move(board_move)?;
+    }
+
+    Ok(board)
+}
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum UciMoveError {
+    #[error("Invalid move `{0}`")]
+    InvalidSyntax(String),
+    #[error("`{0}` is not a legal move")]
+    IllegalMove(String),
+}
+
+impl FastBoard {
+    /// Parses a legal move in long algebraic notation (e.g. `e2e4`, `e7e8q`), where
+    /// castling is given as the king's move (e.g. `e1g1`).
+    pub fn parse_uci_move(&self, text: &str) -> Result<Move, UciMoveError> {
+        let board_move = self
+            .parse_uci_syntax(text)
+            .ok_or_else(|| UciMoveError::InvalidSyntax(text.into()))?;
+
+        if !self.legal_moves().contains(&board_move) {
+            return Err(UciMoveError::IllegalMove(text.into()));
+        }
+
+        Ok(board_move)
+    }
+
+    fn parse_uci_syntax(&self, text: &str) -> Option<Move> {
+        if !text.is_ascii() || !(4..=5).contains(&text.len()) {
+            return None;
+        }
+
+        let from = parse_square(&text[0..2])?;
+        let to = parse_square(&text[2..4])?;
+
+        let is_king = self
+            .mailbox
+            .get(&from)
+            .is_some_and(|piece| piece.kind == PieceType::King);
+
+        if is_king && from.file() == 4 && from.rank() == to.rank() {
+            match to.file() {
+                2 => return Some(Move::Castle(CastleDirection::QueenSide)),
+                6 => return Some(Move::Castle(CastleDirection::KingSide)),
+                _ => {}
+            }
+        }
+
+        let promotion = match &text[4..] {
+            "" => None,
+            "q" => Some(Promotion::Queen),
+            "r" => Some(Promotion::Rook),
+            "b" => Some(Promotion::Bishop),
+            "n" => Some(Promotion::Knight),
+            _ => return None,
+        };
+
+        Some(Move::Direct {
+            from,
+            to,
+            promotion,
+        })
+    }
+}
+
+fn parse_square(text: &str) -> Option<BoardPosition> {
+    let &[file @ b'a'..=b'h', rank @ b'1'..=b'8'] = text.as_bytes() else {
+        return None;
+    };
+
+    Some(BoardPosition::from_rank_file(rank - b'1', file - b'a'))
+}
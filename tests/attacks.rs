@@ -0,0 +1,86 @@
+mod common;
+
+use chess_bot::bitboards::Bitboard;
+use chess_bot::board::FastBoard;
+use chess_bot::piece::PieceColor;
+use common::{square, squares};
+
+#[test]
+fn attackers_to_finds_both_colours() {
+    // e5 is attacked by White's d4 pawn, c4 knight, e2 rook and g3 queen, and by Black's
+    // b8 bishop, e6 king and f6 pawn. The e8 rook stands behind the black king.
+    let board = FastBoard::from_fen("1b2r3/8/4kp2/4r3/2NP4/6Q1/4R3/4K3 w - - 0 1").unwrap();
+    let occupancy = board.bitboards().all_pieces();
+
+    assert_eq!(
+        board.attackers_to(&square("e5"), occupancy),
+        squares(&["d4", "c4", "e2", "g3", "b8", "e6", "f6"])
+    );
+    assert_eq!(
+        board.attackers_to(&square("e5"), occupancy & !squares(&["e6"])),
+        squares(&["d4", "c4", "e2", "g3", "b8", "e6", "f6", "e8"])
+    );
+    assert_eq!(
+        board.attackers_to(&square("h1"), occupancy),
+        Bitboard::empty()
+    );
+}
+
+#[test]
+fn is_square_attacked_respects_colour_and_blockers() {
+    let board = FastBoard::initial();
+
+    assert!(board.is_square_attacked(&square("f3"), PieceColor::White));
+    assert!(!board.is_square_attacked(&square("f3"), PieceColor::Black));
+    assert!(board.is_square_attacked(&square("d6"), PieceColor::Black));
+    assert!(!board.is_square_attacked(&square("e4"), PieceColor::White));
+    // The queen cannot see past her own pawns.
+    assert!(!board.is_square_attacked(&square("a4"), PieceColor::White));
+}
+
+fn static_exchange(fen: &str, uci: &str) -> i32 {
+    let board = FastBoard::from_fen(fen).unwrap();
+    board.static_exchange(board.parse_uci_move(uci).unwrap())
+}
+
+#[test]
+fn static_exchange_plays_out_captures_on_one_square() {
+    // An undefended pawn, and a pawn defended by another pawn.
+    assert_eq!(
+        static_exchange("4k3/8/8/4p3/8/8/8/4QK2 w - - 0 1", "e1e5"),
+        100
+    );
+    assert_eq!(
+        static_exchange("4k3/8/3p4/4p3/8/8/8/4QK2 w - - 0 1", "e1e5"),
+        -800
+    );
+    // The rook on e1 stands behind the one on e2, so White wins the pawn after the
+    // rooks are traded.
+    assert_eq!(
+        static_exchange("4r1k1/8/8/4p3/8/8/4R3/4RK2 w - - 0 1", "e2e5"),
+        100
+    );
+    // Black can stop instead of recapturing at a loss.
+    assert_eq!(
+        static_exchange("3r2k1/8/8/3n4/8/2N5/8/3RK3 w - - 0 1", "c3d5"),
+        320
+    );
+    assert_eq!(
+        static_exchange("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6"),
+        100
+    );
+    assert_eq!(static_exchange(&FastBoard::initial().to_fen(), "e2e4"), 0);
+}
+
+#[test]
+fn static_exchange_never_gives_up_the_king() {
+    // The king may recapture the knight unless the rook on d1 guards d5.
+    assert_eq!(
+        static_exchange("8/8/4k3/3p4/8/2N5/8/4K3 w - - 0 1", "c3d5"),
+        -220
+    );
+    assert_eq!(
+        static_exchange("8/8/4k3/3p4/8/2N5/8/3RK3 w - - 0 1", "c3d5"),
+        100
+    );
+}
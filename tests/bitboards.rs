@@ -0,0 +1,88 @@
+mod common;
+
+use chess_bot::bitboards::{
+    between, line, Bitboard, ANTI_DIAGONALS, DIAGONALS, FILES, LIGHT_SQUARES, RANKS,
+};
+use chess_bot::board::FastBoard;
+use common::{square, squares};
+
+#[test]
+fn bitboards_iterate_and_count_squares() {
+    let mut set = squares(&["a1", "d4", "h8"]);
+
+    assert_eq!(set.filled_indices().collect::<Vec<_>>(), vec![0, 27, 63]);
+    assert_eq!(set.into_iter().len(), 3);
+    assert_eq!(set.count(), 3);
+    assert_eq!(set.lsb(), Some(0));
+    assert!(set.more_than_one());
+
+    assert_eq!(set.pop_lsb(), Some(0));
+    assert_eq!(set.pop_lsb(), Some(27));
+    assert!(!set.more_than_one());
+    assert_eq!(set.pop_lsb(), Some(63));
+    assert_eq!(set.pop_lsb(), None);
+    assert_eq!(set.lsb(), None);
+    assert!(!set.more_than_one());
+}
+
+#[test]
+fn bitboard_shifts_stop_at_the_edges() {
+    let corners = squares(&["a1", "h1", "a8", "h8"]);
+
+    assert_eq!(corners.north(), squares(&["a2", "h2"]));
+    assert_eq!(corners.south(), squares(&["a7", "h7"]));
+    assert_eq!(corners.east(), squares(&["b1", "b8"]));
+    assert_eq!(corners.west(), squares(&["g1", "g8"]));
+    assert_eq!(FILES[7].east(), Bitboard::empty());
+    assert_eq!(FILES[3].west(), FILES[2]);
+}
+
+#[test]
+fn bitboard_masks_cover_the_board() {
+    let union = |masks: &[Bitboard]| masks.iter().fold(Bitboard::empty(), |a, &b| a | b);
+
+    for masks in [&FILES[..], &RANKS[..], &DIAGONALS[..], &ANTI_DIAGONALS[..]] {
+        assert_eq!(union(masks), Bitboard::ones());
+        assert_eq!(masks.iter().map(Bitboard::count).sum::<u32>(), 64);
+    }
+
+    assert_eq!(
+        RANKS[3],
+        squares(&["a4", "b4", "c4", "d4", "e4", "f4", "g4", "h4"])
+    );
+    assert_eq!(DIAGONALS[7].count(), 8);
+    assert!(DIAGONALS[7].get(0) && DIAGONALS[7].get(63));
+    assert!(ANTI_DIAGONALS[7].get(7) && ANTI_DIAGONALS[7].get(56));
+    assert_eq!(LIGHT_SQUARES.count(), 32);
+    assert!(!LIGHT_SQUARES.get(square("a1").index()));
+}
+
+#[test]
+fn bitboard_lines_between_squares() {
+    assert_eq!(
+        between(&square("a1"), &square("d4")),
+        squares(&["b2", "c3"])
+    );
+    assert_eq!(
+        between(&square("e8"), &square("e5")),
+        squares(&["e7", "e6"])
+    );
+    assert_eq!(between(&square("c3"), &square("d3")), Bitboard::empty());
+    assert_eq!(between(&square("a1"), &square("b3")), Bitboard::empty());
+
+    assert_eq!(line(&square("c3"), &square("e5")), DIAGONALS[7]);
+    assert_eq!(line(&square("b7"), &square("c6")), ANTI_DIAGONALS[7]);
+    assert_eq!(line(&square("h2"), &square("b2")), RANKS[1]);
+    assert_eq!(line(&square("a1"), &square("b3")), Bitboard::empty());
+}
+
+#[test]
+fn bitboards_combine_both_colours() {
+    let board = FastBoard::initial();
+    let bitboards = board.bitboards();
+
+    assert_eq!(bitboards.queens(), squares(&["d1", "d8"]));
+    assert_eq!(bitboards.kings(), squares(&["e1", "e8"]));
+    assert_eq!(bitboards.rooks(), squares(&["a1", "h1", "a8", "h8"]));
+    assert_eq!(bitboards.pawns(), RANKS[1] | RANKS[6]);
+}
@@ -0,0 +1,14 @@
+use chess_bot::bitboards::Bitboard;
+use chess_bot::position::BoardPosition;
+
+/// Parses a square name such as `e4`.
+pub fn square(name: &str) -> BoardPosition {
+    let bytes = name.as_bytes();
+    BoardPosition::from_rank_file(bytes[1] - b'1', bytes[0] - b'a')
+}
+
+pub fn squares(names: &[&str]) -> Bitboard {
+    names.iter().fold(Bitboard::empty(), |set, name| {
+        set | Bitboard::from_pos(&square(name))
+    })
+}
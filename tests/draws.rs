@@ -0,0 +1,121 @@
+use chess_bot::board::FastBoard;
+use chess_bot::search::{EndState, MoveEngine, Score};
+
+fn search(fen: &str, depth: u32) -> Score {
+    let mut board = FastBoard::from_fen(fen).unwrap();
+    let mut engine = MoveEngine::new();
+
+    engine
+        .alpha_beta(
+            &mut board,
+            depth,
+            0,
+            Score::initial_alpha(),
+            Score::initial_beta(),
+            None,
+        )
+        .unwrap()
+}
+
+#[test]
+fn fifty_move_rule_draws_even_when_evasions_reset_the_clock() {
+    // The only evasion is capturing the rook, which would reset the clock.
+    let fen = "kR6/pp6/8/8/8/8/8/6K1 b - - 100 80";
+
+    let mut board = FastBoard::from_fen(fen).unwrap();
+    assert!(matches!(
+        MoveEngine::new().get_end_state(&mut board),
+        Some(EndState::FiftyMoveRule)
+    ));
+    assert_eq!(search(fen, 3), Score::exact(0));
+}
+
+#[test]
+fn checkmate_takes_precedence_over_the_fifty_move_rule() {
+    let fen = "k7/1Q6/1K6/8/8/8/8/8 b - - 100 80";
+
+    let mut board = FastBoard::from_fen(fen).unwrap();
+    assert!(matches!(
+        MoveEngine::new().get_end_state(&mut board),
+        Some(EndState::Checkmate)
+    ));
+    assert_eq!(search(fen, 3), Score::mated_in(0));
+}
+
+#[test]
+fn clocks_are_kept_through_make_and_unmake() {
+    let mut board = FastBoard::from_fen("4k3/4p3/8/8/8/8/8/R3K3 w - - 7 20").unwrap();
+    let mut clocks = vec![(board.halfmove_clock(), board.fullmoves())];
+
+    // A quiet move, a pawn move, a quiet move and then a capture.
+    for san in ["Ra2", "e5", "Ke2", "Kd7", "Ra5", "Kc6", "Rxe5"] {
+        let board_move = board.parse_san(san).unwrap();
+        board.make_move(board_move).unwrap();
+        clocks.push((board.halfmove_clock(), board.fullmoves()));
+    }
+
+    assert_eq!(
+        clocks,
+        vec![
+            (7, 20),
+            (8, 20),
+            (0, 21),
+            (1, 21),
+            (2, 22),
+            (3, 22),
+            (4, 23),
+            (0, 23),
+        ]
+    );
+
+    while clocks.len() > 1 {
+        board.unmake_last_move();
+        clocks.pop();
+        assert_eq!(
+            (board.halfmove_clock(), board.fullmoves()),
+            *clocks.last().unwrap()
+        );
+    }
+    assert_eq!(board.to_fen(), "4k3/4p3/8/8/8/8/8/R3K3 w - - 7 20");
+}
+
+#[test]
+fn insufficient_material_is_detected() {
+    for (fen, insufficient) in [
+        ("8/8/4k3/8/8/4K3/8/8 w - - 0 1", true),
+        ("8/8/4k3/8/8/4KB2/8/8 w - - 0 1", true),
+        ("8/8/4k3/8/8/4KN2/8/8 w - - 0 1", true),
+        // Bishops which all stand on light squares can never mate.
+        ("8/8/4k3/3b4/8/4KB2/8/8 w - - 0 1", true),
+        ("2b5/8/4k3/8/8/4KB2/8/8 w - - 0 1", true),
+        ("8/8/4k3/4b3/8/4KB2/8/8 w - - 0 1", false),
+        ("8/8/4k3/8/8/4KN2/4N3/8 w - - 0 1", false),
+        ("8/8/4k1n1/8/8/4KB2/8/8 w - - 0 1", false),
+        ("8/8/4k3/8/8/4K3/4P3/8 w - - 0 1", false),
+        ("8/3p4/4k3/8/8/4KB2/8/8 w - - 0 1", false),
+        ("8/8/4k3/8/8/4K3/8/7R w - - 0 1", false),
+    ] {
+        let board = FastBoard::from_fen(fen).unwrap();
+        assert_eq!(board.has_insufficient_material(), insufficient, "{}", fen);
+    }
+}
+
+#[test]
+fn insufficient_material_ends_the_game() {
+    let mut board = FastBoard::from_fen("8/8/4k3/8/8/4KB2/8/8 b - - 0 1").unwrap();
+    assert!(matches!(
+        MoveEngine::new().get_end_state(&mut board),
+        Some(EndState::InsufficientMaterial)
+    ));
+
+    // Capturing the last pawn leaves bare kings.
+    let mut board = FastBoard::from_fen("8/8/4k3/4P3/8/4K3/8/8 b - - 0 1").unwrap();
+    let mut engine = MoveEngine::new();
+    assert!(engine.get_end_state(&mut board).is_none());
+
+    board.make_move(board.parse_san("Kxe5").unwrap()).unwrap();
+    assert!(matches!(
+        engine.get_end_state(&mut board),
+        Some(EndState::InsufficientMaterial)
+    ));
+}
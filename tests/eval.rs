@@ -0,0 +1,36 @@
+use chess_bot::eval::EvalParams;
+
+#[test]
+fn eval_params_round_trip_through_text() {
+    let mut params = EvalParams::default();
+    params.piece_values[1] = 335;
+    params.endgame_pst[0][12] = -7;
+    params.max_king_attack_bonus = 123;
+
+    let text = params.to_text();
+    assert_eq!(EvalParams::from_text(&text).unwrap(), params);
+}
+
+#[test]
+fn eval_params_keep_defaults_for_missing_names() {
+    let params = EvalParams::from_text("# tuned\n\nmax_king_attack_bonus 42\n").unwrap();
+
+    assert_eq!(params.max_king_attack_bonus, 42);
+    assert_eq!(params.piece_values, EvalParams::default().piece_values);
+}
+
+#[test]
+fn eval_params_reject_bad_lines() {
+    let error = EvalParams::from_text("queen_value 900").unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("unknown parameter `queen_value`"));
+
+    let error = EvalParams::from_text("piece_values 100 320 330 500 900").unwrap_err();
+    assert!(error.to_string().contains("expected 6 values"));
+
+    let error = EvalParams::from_text("doubled_penalty 10 15 20").unwrap_err();
+    assert!(error.to_string().contains("expected 2 values"));
+
+    assert!(EvalParams::from_text("doubled_penalty 10 x").is_err());
+}
@@ -0,0 +1,160 @@
+use chess_bot::board::{FastBoard, FenError};
+use chess_bot::piece::PieceColor;
+use proptest::prelude::*;
+
+const STARTPOS: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+const POSITION_3: &str = "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1";
+const POSITION_4: &str = "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1";
+const POSITION_5: &str = "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8";
+
+/// Plays the legal move picked by each choice in turn, stopping early if the game ends.
+fn play_random_moves(fen: &str, choices: &[usize]) -> FastBoard {
+    let mut board = FastBoard::from_fen(fen).unwrap();
+
+    for choice in choices {
+        let legal_moves = board.legal_moves();
+        if legal_moves.is_empty() {
+            break;
+        }
+        board
+            .make_move(legal_moves[choice % legal_moves.len()])
+            .unwrap();
+    }
+
+    board
+}
+
+proptest! {
+    #[test]
+    fn fen_round_trips(
+        fen in prop::sample::select(vec![STARTPOS, KIWIPETE, POSITION_3, POSITION_4, POSITION_5]),
+        choices in prop::collection::vec(any::<usize>(), 0..40),
+    ) {
+        let board = play_random_moves(fen, &choices);
+        let fen = board.to_fen();
+        let parsed = FastBoard::from_fen(&fen).unwrap();
+
+        // The parsed board has no move history, but its position must be the same.
+        prop_assert_eq!(parsed.bitboards(), board.bitboards());
+        prop_assert_eq!(parsed.next_to_move(), board.next_to_move());
+        prop_assert_eq!(parsed.castling_rights(), board.castling_rights());
+        prop_assert_eq!(parsed.en_passant_target(), board.en_passant_target());
+        prop_assert_eq!(parsed.halfmove_clock(), board.halfmove_clock());
+        prop_assert_eq!(parsed.fullmoves(), board.fullmoves());
+        prop_assert_eq!(parsed.zobrist_key(), board.zobrist_key());
+        prop_assert_eq!(parsed.evaluate(), board.evaluate());
+        prop_assert_eq!(parsed.to_fen(), fen);
+    }
+}
+
+#[test]
+fn fen_keeps_en_passant_square() {
+    let fen = "rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 3";
+    assert_eq!(FastBoard::from_fen(fen).unwrap().to_fen(), fen);
+}
+
+#[test]
+fn fen_writes_empty_castling_rights() {
+    let fen = "4k3/8/8/8/8/8/8/4K3 w - - 12 40";
+    assert_eq!(FastBoard::from_fen(fen).unwrap().to_fen(), fen);
+}
+
+#[test]
+fn fen_rejects_malformed_fields() {
+    // Long enough to overflow the file counter if it were not checked on each digit.
+    let long_rank = format!(
+        "rnbqkbnr/pppppppp/{}/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "8".repeat(40)
+    );
+
+    let cases = [
+        (
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -",
+            FenError::FieldCount(4),
+        ),
+        (
+            "rnbqkbnr/pppppppp/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            FenError::RankCount(7),
+        ),
+        (
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNX w KQkq - 0 1",
+            FenError::InvalidPiece('X'),
+        ),
+        (
+            "rnbqkbnr/pppppppp/9/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            FenError::InvalidPiece('9'),
+        ),
+        (
+            "rnbqkbnr/pppppppp/7/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            FenError::RankLength { rank: 6 },
+        ),
+        (
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNRR w KQkq - 0 1",
+            FenError::RankLength { rank: 1 },
+        ),
+        (
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR1 w KQkq - 0 1",
+            FenError::RankLength { rank: 1 },
+        ),
+        (long_rank.as_str(), FenError::RankLength { rank: 6 }),
+        (
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR x KQkq - 0 1",
+            FenError::InvalidSideToMove("x".into()),
+        ),
+        (
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KKq - 0 1",
+            FenError::InvalidCastlingRights("KKq".into()),
+        ),
+        (
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq e3 0 1",
+            FenError::InvalidEnPassant("e3".into()),
+        ),
+        (
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq e6 0 1",
+            FenError::InvalidEnPassant("e6".into()),
+        ),
+        (
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - -1 1",
+            FenError::InvalidHalfmoveClock("-1".into()),
+        ),
+        (
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 0",
+            FenError::InvalidFullmoveNumber("0".into()),
+        ),
+    ];
+
+    for (fen, error) in cases {
+        assert_eq!(FastBoard::from_fen(fen), Err(error), "{}", fen);
+    }
+}
+
+#[test]
+fn fen_rejects_impossible_positions() {
+    let cases = [
+        (
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQ1BNR w kq - 0 1",
+            FenError::KingCount {
+                color: PieceColor::White,
+                count: 0,
+            },
+        ),
+        (
+            "k7/8/8/8/8/8/8/K6k w - - 0 1",
+            FenError::KingCount {
+                color: PieceColor::Black,
+                count: 2,
+            },
+        ),
+        ("k6P/8/8/8/8/8/8/K7 w - - 0 1", FenError::PawnOnBackRank),
+        (
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBN1 w KQkq - 0 1",
+            FenError::CastlingWithoutPieces(PieceColor::White),
+        ),
+        ("k6R/8/8/8/8/8/8/K7 w - - 0 1", FenError::OpponentInCheck),
+    ];
+
+    for (fen, error) in cases {
+        assert_eq!(FastBoard::from_fen(fen), Err(error), "{}", fen);
+    }
+}
@@ -0,0 +1,57 @@
+use chess_bot::board::FastBoard;
+use chess_bot::movement::{CastleDirection, Move, PackedMove, Promotion};
+
+const POSITIONS: [&str; 4] = [
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R b KQkq - 0 1",
+    "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1",
+    "8/8/8/2k5/3Pp3/8/8/4K3 b - d3 0 1",
+];
+
+#[test]
+fn packed_moves_round_trip() {
+    for fen in POSITIONS {
+        let board = FastBoard::from_fen(fen).unwrap();
+
+        for &board_move in board.legal_moves().iter() {
+            let packed = PackedMove::new(&board, board_move);
+
+            assert_eq!(Move::from(packed), board_move, "{} in {}", board_move, fen);
+            assert_eq!(PackedMove::from_bits(packed.bits()), Some(packed));
+        }
+    }
+}
+
+#[test]
+fn packed_moves_record_move_kinds() {
+    let board = FastBoard::from_fen(POSITIONS[1]).unwrap();
+    let castle = PackedMove::new(&board, Move::Castle(CastleDirection::QueenSide));
+    assert_eq!(castle.castle(), Some(CastleDirection::QueenSide));
+    assert_eq!(
+        (castle.from().to_string(), castle.to().to_string()),
+        ("e8".into(), "c8".into())
+    );
+    assert!(castle.is_quiet());
+
+    let board = FastBoard::from_fen(POSITIONS[2]).unwrap();
+    let promotion = PackedMove::new(&board, board.parse_san("gxh1=N").unwrap());
+    assert_eq!(promotion.promotion(), Some(Promotion::Knight));
+    assert!(promotion.is_capture());
+    assert!(!promotion.is_quiet());
+
+    let board = FastBoard::from_fen(POSITIONS[3]).unwrap();
+    let en_passant = PackedMove::new(&board, board.parse_san("exd3").unwrap());
+    assert!(en_passant.is_en_passant());
+    assert!(en_passant.is_capture());
+    assert_eq!(en_passant.castle(), None);
+
+    let quiet = PackedMove::new(&board, board.parse_san("Kd5").unwrap());
+    assert!(quiet.is_quiet());
+    assert!(!quiet.is_capture());
+}
+
+#[test]
+fn packed_moves_fit_in_two_bytes() {
+    assert_eq!(size_of::<Option<PackedMove>>(), 2);
+    assert_eq!(PackedMove::from_bits(0), None);
+}
@@ -0,0 +1,16 @@
+use chess_bot::board::FastBoard;
+use chess_bot::ordering::{MoveOrdering, MovePicker};
+
+#[test]
+fn move_picker_tries_losing_captures_last() {
+    // Qxa5 wins a pawn, while the pawn on e5 is defended by the one on d6.
+    let board = FastBoard::from_fen("4k3/8/3p4/p3p3/8/8/8/4QK2 w - - 0 1").unwrap();
+    let moves = board.legal_moves();
+    let count = moves.len();
+
+    let picked: Vec<_> = MovePicker::new(&board, moves, None, &MoveOrdering::new(), 0).collect();
+
+    assert_eq!(picked.len(), count);
+    assert_eq!(picked.first(), Some(&board.parse_uci_move("e1a5").unwrap()));
+    assert_eq!(picked.last(), Some(&board.parse_uci_move("e1e5").unwrap()));
+}
@@ -0,0 +1,109 @@
+use chess_bot::board::{FastBoard, MoveError};
+use proptest::prelude::*;
+
+const STARTPOS: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+const POSITION_3: &str = "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1";
+const POSITION_4: &str = "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1";
+const POSITION_5: &str = "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8";
+const POSITION_6: &str = "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10";
+
+fn assert_perft(fen: &str, expected: &[u64]) {
+    let mut board = FastBoard::from_fen(fen).unwrap();
+
+    for (depth, &nodes) in expected.iter().enumerate() {
+        let depth = depth as u32 + 1;
+        assert_eq!(board.perft(depth), nodes, "perft({}) of {}", depth, fen);
+    }
+}
+
+#[test]
+fn perft_startpos() {
+    assert_perft(STARTPOS, &[20, 400, 8902, 197281]);
+}
+
+#[test]
+fn perft_kiwipete() {
+    assert_perft(KIWIPETE, &[48, 2039, 97862]);
+}
+
+#[test]
+fn perft_position_3() {
+    assert_perft(POSITION_3, &[14, 191, 2812, 43238]);
+}
+
+#[test]
+fn perft_position_4() {
+    assert_perft(POSITION_4, &[6, 264, 9467]);
+}
+
+#[test]
+fn perft_position_5() {
+    assert_perft(POSITION_5, &[44, 1486, 62379]);
+}
+
+#[test]
+fn perft_position_6() {
+    assert_perft(POSITION_6, &[46, 2079, 89890]);
+}
+
+#[test]
+fn perft_en_passant_horizontal_pin() {
+    // Capturing en passant would remove both pawns from the fifth rank, exposing
+    // the white king to the rook.
+    assert_perft("8/8/8/KPp4r/8/8/8/7k w - c6 0 2", &[4]);
+    assert_perft("8/8/8/KPp5/8/8/8/7k w - c6 0 2", &[5]);
+}
+
+#[test]
+fn divide_sums_to_perft() {
+    let mut board = FastBoard::from_fen(KIWIPETE).unwrap();
+    let divided = board.divide(2);
+
+    assert_eq!(divided.len(), 48);
+    assert_eq!(divided.iter().map(|(_, nodes)| nodes).sum::<u64>(), 2039);
+}
+
+#[test]
+fn perft_checks_and_pins() {
+    // Double check, where the knight may not take the rook: only the king may move.
+    assert_perft("4k3/8/8/8/1b6/8/2N5/r3K3 w - - 0 1", &[2]);
+    // A pinned knight cannot move, and a pinned rook only along the pin.
+    assert_perft("4k3/4r3/8/b7/8/2N1R3/8/4K3 w - - 0 1", &[10]);
+    // En passant capturing the checking pawn.
+    assert_perft("8/8/8/2k5/3Pp3/8/8/4K3 b - d3 0 1", &[9]);
+}
+
+proptest! {
+    #[test]
+    fn legal_moves_match_pseudo_moves_that_can_be_made(
+        fen in prop::sample::select(vec![STARTPOS, KIWIPETE, POSITION_3, POSITION_4, POSITION_5]),
+        choices in prop::collection::vec(any::<usize>(), 0..30),
+    ) {
+        let mut board = FastBoard::from_fen(fen).unwrap();
+
+        for choice in choices {
+            let mut expected = Vec::new();
+            for board_move in board.calculate_pseudo_moves() {
+                let movement = board.make_move(board_move);
+                if let Err(MoveError::IllegalMove) = movement {
+                    continue;
+                }
+                movement.unwrap();
+                board.unmake_last_move();
+                expected.push(board_move);
+            }
+
+            let legal_moves = board.legal_moves();
+            prop_assert_eq!(legal_moves.len(), expected.len(), "{}", board.to_fen());
+            for board_move in &expected {
+                prop_assert!(legal_moves.contains(board_move), "{} in {}", board_move, board.to_fen());
+            }
+
+            if legal_moves.is_empty() {
+                break;
+            }
+            board.make_move(legal_moves[choice % legal_moves.len()]).unwrap();
+        }
+    }
+}
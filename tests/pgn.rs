@@ -0,0 +1,145 @@
+use chess_bot::board::FastBoard;
+use chess_bot::movement::{CastleDirection, Move};
+use chess_bot::pgn::{self, eval_comment, Game, GameResult, PgnError};
+use chess_bot::san::SanError;
+use chess_bot::search::Score;
+
+const ANNOTATED: &str = r#"
+[Event "Casual \"blitz\" game"]
+[Site "?"]
+[Date "2024.01.01"]
+[Round "1"]
+[White "Alice"]
+[Black "Bob"]
+[Result "0-1"]
+[ECO "C20"]
+
+% An escaped line, ignored entirely.
+1. e4 {King's pawn} e5 2. Nf3 $1 Nc6 (2... d6 3. d4 (3. Bc4) exd4) 3.Bc4 Nd4?!
+4. Nxe5 ; Grabbing the pawn
+Qg5 5. Nxf7 Qxg2 6. Rf1 Qxe4+ 7. Be2 Nf3# 0-1
+
+[Event "Second"]
+[SetUp "1"]
+[FEN "4k3/8/8/8/8/8/4P3/4K3 b - - 3 40"]
+
+40... Kd7 41. e4 *
+
+1. e4 e5 2. Nf3 Nc6 3. Bc4 Bc5 4. 0-0 d6 5. d3 Be6 6. Nc3 Qd7 7. Bxe6 Qxe6
+8. Be3 0-0-0 *
+"#;
+
+#[test]
+fn pgn_parses_tags_comments_and_skips_variations() {
+    let games = pgn::parse_games(ANNOTATED).unwrap();
+    assert_eq!(games.len(), 3);
+
+    let game = &games[0];
+    assert_eq!(game.tag("Event"), Some("Casual \"blitz\" game"));
+    assert_eq!(game.tag("ECO"), Some("C20"));
+    assert_eq!(game.result, GameResult::BlackWins);
+    assert_eq!(game.moves.len(), 14);
+    assert_eq!(game.moves[0].comment.as_deref(), Some("King's pawn"));
+    assert_eq!(game.moves[6].comment.as_deref(), Some("Grabbing the pawn"));
+    assert_eq!(game.moves[1].comment, None);
+
+    let positions = game.positions().unwrap();
+    assert_eq!(positions.len(), 15);
+    assert_eq!(
+        positions.last().unwrap().to_fen(),
+        "r1b1kbnr/pppp1Npp/8/8/4q3/5n2/PPPPBP1P/RNBQKR2 w Qkq - 2 8"
+    );
+
+    let game = &games[1];
+    assert_eq!(game.result, GameResult::Unknown);
+    assert_eq!(
+        game.positions().unwrap().last().unwrap().to_fen(),
+        "8/3k4/8/8/4P3/8/8/4K3 b - e3 0 41"
+    );
+
+    let game = &games[2];
+    assert_eq!(game.moves.len(), 16);
+    assert_eq!(
+        game.moves[6].board_move,
+        Move::Castle(CastleDirection::KingSide)
+    );
+    assert_eq!(
+        game.moves[15].board_move,
+        Move::Castle(CastleDirection::QueenSide)
+    );
+}
+
+#[test]
+fn pgn_round_trips_written_games() {
+    for game in pgn::parse_games(ANNOTATED).unwrap() {
+        let written = game.to_pgn().unwrap();
+        let parsed = pgn::parse_games(&written).unwrap();
+
+        assert_eq!(parsed, vec![game], "{}", written);
+    }
+}
+
+#[test]
+fn pgn_writes_game_from_board() {
+    let mut board = FastBoard::from_fen("4k3/8/8/8/8/8/4P3/4K3 b - - 3 40").unwrap();
+    for san in ["Kd7", "e4", "Kc6"] {
+        let board_move = board.parse_san(san).unwrap();
+        board.make_move(board_move).unwrap();
+    }
+
+    let mut game = Game::from_board(&board, GameResult::Draw);
+    game.moves[1].comment = Some(eval_comment(Score::exact(35), 12));
+    game.set_tag("White", "Alice");
+
+    assert_eq!(
+        game.to_pgn().unwrap(),
+        concat!(
+            "[Event \"?\"]\n",
+            "[Site \"?\"]\n",
+            "[Date \"????.??.??\"]\n",
+            "[Round \"?\"]\n",
+            "[White \"Alice\"]\n",
+            "[Black \"?\"]\n",
+            "[Result \"1/2-1/2\"]\n",
+            "[SetUp \"1\"]\n",
+            "[FEN \"4k3/8/8/8/8/8/4P3/4K3 b - - 3 40\"]\n",
+            "\n",
+            "40... Kd7 41. e4 {+0.35/12} 41... Kc6 1/2-1/2\n",
+        )
+    );
+}
+
+#[test]
+fn pgn_formats_mate_evaluations() {
+    assert_eq!(eval_comment(Score::mate_in(5), 9), "+M3/9");
+    assert_eq!(eval_comment(Score::mated_in(4), 9), "-M2/9");
+    assert_eq!(eval_comment(Score::exact(-120), 3), "-1.20/3");
+}
+
+#[test]
+fn pgn_rejects_malformed_games() {
+    assert!(matches!(
+        pgn::parse_games("1. e4 {unterminated"),
+        Err(PgnError::Unterminated("comment"))
+    ));
+    assert!(matches!(
+        pgn::parse_games("1. e4 (1. d4 *"),
+        Err(PgnError::UnbalancedVariation)
+    ));
+    assert!(matches!(
+        pgn::parse_games("[Event]\n1. e4 *"),
+        Err(PgnError::InvalidTag(_))
+    ));
+    assert!(matches!(
+        pgn::parse_games("1. e4 e5 2. Ke3 *"),
+        Err(PgnError::InvalidMove { number: 2, .. })
+    ));
+    assert!(matches!(
+        pgn::parse_games("1. Né3 *"),
+        Err(PgnError::InvalidMove {
+            number: 1,
+            source: SanError::InvalidSyntax(_),
+            ..
+        })
+    ));
+}
@@ -0,0 +1,108 @@
+use chess_bot::board::FastBoard;
+use chess_bot::san::SanError;
+
+const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+const POSITION_4: &str = "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1";
+
+fn legal_sans(fen: &str) -> Vec<String> {
+    let mut board = FastBoard::from_fen(fen).unwrap();
+    let mut sans: Vec<_> = board
+        .legal_moves()
+        .into_iter()
+        .map(|board_move| board.move_to_san(board_move))
+        .collect();
+
+    sans.sort();
+    sans
+}
+
+#[test]
+fn san_round_trips_every_legal_move() {
+    for fen in [KIWIPETE, POSITION_4] {
+        let mut board = FastBoard::from_fen(fen).unwrap();
+
+        for board_move in board.legal_moves() {
+            let san = board.move_to_san(board_move);
+            assert_eq!(board.parse_san(&san), Ok(board_move), "{} in {}", san, fen);
+        }
+    }
+}
+
+#[test]
+fn san_formats_captures_castling_and_promotions() {
+    let sans = legal_sans(KIWIPETE);
+    for san in ["O-O", "O-O-O", "Nxf7", "dxe6", "Qxf6", "Bxa6", "gxh3"] {
+        assert!(sans.contains(&san.to_string()), "{} not in {:?}", san, sans);
+    }
+
+    let sans = legal_sans("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1");
+    for san in ["b8=Q+", "b8=R+", "b8=B", "b8=N"] {
+        assert!(sans.contains(&san.to_string()), "{} not in {:?}", san, sans);
+    }
+}
+
+#[test]
+fn san_disambiguates_by_file_then_rank() {
+    let sans = legal_sans("4k3/8/8/8/8/8/4K3/R6R w - - 0 1");
+    assert!(sans.contains(&"Rad1".to_string()));
+    assert!(sans.contains(&"Rhd1".to_string()));
+
+    let sans = legal_sans("R7/7k/8/8/8/8/8/R3K3 w - - 0 1");
+    assert!(sans.contains(&"R1a4".to_string()));
+    assert!(sans.contains(&"R8a4".to_string()));
+
+    let sans = legal_sans("4k3/8/8/8/8/Q1Q5/8/Q3K3 w - - 0 1");
+    assert!(sans.contains(&"Qa3b2".to_string()));
+}
+
+#[test]
+fn san_marks_check_and_mate() {
+    let sans = legal_sans("6k1/5ppp/8/8/8/8/8/R3K3 w - - 0 1");
+    assert!(sans.contains(&"Ra8#".to_string()));
+    assert!(!sans.contains(&"Ra8+".to_string()));
+
+    let sans = legal_sans("6k1/8/8/8/8/8/8/R3K3 w - - 0 1");
+    assert!(sans.contains(&"Ra8+".to_string()));
+}
+
+#[test]
+fn san_parses_loose_input() {
+    let board = FastBoard::initial();
+    let knight = board.parse_san("Nf3").unwrap();
+    assert_eq!(board.parse_san("Ng1f3+!?"), Ok(knight));
+    assert_eq!(board.parse_san("e4"), board.parse_san("e2e4"));
+
+    let board = FastBoard::from_fen("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+    assert_eq!(board.parse_san("b8Q"), board.parse_san("b8=Q"));
+}
+
+#[test]
+fn san_rejects_bad_moves() {
+    let board = FastBoard::from_fen("4k3/8/8/8/8/8/4K3/R6R w - - 0 1").unwrap();
+
+    assert_eq!(
+        board.parse_san("Rd1"),
+        Err(SanError::AmbiguousMove("Rd1".into()))
+    );
+    assert_eq!(
+        board.parse_san("Nf3"),
+        Err(SanError::NoMatchingMove("Nf3".into()))
+    );
+    assert_eq!(
+        board.parse_san("Rz9"),
+        Err(SanError::InvalidSyntax("Rz9".into()))
+    );
+    assert_eq!(board.parse_san(""), Err(SanError::InvalidSyntax("".into())));
+}
+
+#[test]
+fn san_rejects_non_ascii_input() {
+    let board = FastBoard::initial();
+
+    for san in ["é4", "Né3", "Nf3é", "e4\u{a0}"] {
+        assert_eq!(
+            board.parse_san(san),
+            Err(SanError::InvalidSyntax(san.into()))
+        );
+    }
+}
@@ -0,0 +1,193 @@
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use chess_bot::board::FastBoard;
+use chess_bot::movement::{CastleDirection, Move};
+use chess_bot::uci::{self, UciMoveError};
+
+const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+const KIWIPETE_BLACK: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R b KQkq - 0 1";
+
+#[test]
+fn uci_moves_round_trip() {
+    for fen in [KIWIPETE, KIWIPETE_BLACK] {
+        let board = FastBoard::from_fen(fen).unwrap();
+
+        for board_move in board.legal_moves() {
+            let text = board_move.to_uci(board.next_to_move());
+            assert_eq!(
+                board.parse_uci_move(&text),
+                Ok(board_move),
+                "{} in {}",
+                text,
+                fen
+            );
+        }
+    }
+}
+
+#[test]
+fn uci_maps_king_moves_to_castling() {
+    let board = FastBoard::from_fen(KIWIPETE).unwrap();
+    assert_eq!(
+        board.parse_uci_move("e1g1"),
+        Ok(Move::Castle(CastleDirection::KingSide))
+    );
+    assert_eq!(
+        board.parse_uci_move("e1c1"),
+        Ok(Move::Castle(CastleDirection::QueenSide))
+    );
+
+    let board = FastBoard::from_fen(KIWIPETE_BLACK).unwrap();
+    assert_eq!(
+        board.parse_uci_move("e8g8"),
+        Ok(Move::Castle(CastleDirection::KingSide))
+    );
+    assert_eq!(
+        Move::Castle(CastleDirection::QueenSide).to_uci(board.next_to_move()),
+        "e8c8"
+    );
+}
+
+#[test]
+fn uci_rejects_bad_moves() {
+    let board = FastBoard::initial();
+
+    for text in ["e2e", "e2e4x", "i2i4", "e7e8k"] {
+        assert_eq!(
+            board.parse_uci_move(text),
+            Err(UciMoveError::InvalidSyntax(text.into()))
+        );
+    }
+    for text in ["e2e5", "e1g1", "e7e5", "a7a8q"] {
+        assert_eq!(
+            board.parse_uci_move(text),
+            Err(UciMoveError::IllegalMove(text.into()))
+        );
+    }
+}
+
+/// Collects the engine's output where the test can read it after the session ends.
+#[derive(Clone, Default)]
+struct SharedOutput(Arc<Mutex<Vec<u8>>>);
+
+impl SharedOutput {
+    fn lines(&self) -> Vec<String> {
+        let output = self.0.lock().unwrap();
+        String::from_utf8_lossy(&output)
+            .lines()
+            .map(String::from)
+            .collect()
+    }
+}
+
+impl Write for SharedOutput {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn uci_session_answers_commands() {
+    let input = "uci\nisready\nposition startpos moves e2e4 e7e5\ngo depth 2\n";
+    let output = SharedOutput::default();
+
+    uci::run(input.as_bytes(), output.clone()).unwrap();
+
+    let lines = output.lines();
+    assert!(lines.contains(&"uciok".to_string()), "{:?}", lines);
+    assert!(lines.contains(&"readyok".to_string()), "{:?}", lines);
+    assert!(lines.iter().any(|line| line.starts_with("info depth 2")));
+
+    let best_move = lines
+        .last()
+        .and_then(|line| line.strip_prefix("bestmove "))
+        .unwrap_or_else(|| panic!("no bestmove in {:?}", lines));
+
+    let mut board = FastBoard::initial();
+    for text in ["e2e4", "e7e5"] {
+        board
+            .make_move(board.parse_uci_move(text).unwrap())
+            .unwrap();
+    }
+    assert!(board.parse_uci_move(best_move).is_ok(), "{}", best_move);
+}
+
+#[test]
+fn uci_infinite_search_waits_for_stop() {
+    // With bare kings the search reaches its depth cap almost immediately.
+    let (mut input, reader) = pipe();
+    let output = SharedOutput::default();
+    let session = {
+        let output = output.clone();
+        thread::spawn(move || uci::run(reader, output))
+    };
+
+    writeln!(input, "position fen 8/8/4k3/8/8/4K3/8/8 w - - 0 1").unwrap();
+    writeln!(input, "go infinite").unwrap();
+    thread::sleep(Duration::from_millis(200));
+    assert!(!output
+        .lines()
+        .iter()
+        .any(|line| line.starts_with("bestmove")));
+
+    writeln!(input, "stop").unwrap();
+    writeln!(input, "quit").unwrap();
+    drop(input);
+    session.join().unwrap().unwrap();
+
+    assert!(output.lines().last().unwrap().starts_with("bestmove "));
+}
+
+/// A writer whose lines can be read back, one at a time, as a `BufRead`.
+fn pipe() -> (PipeWriter, io::BufReader<PipeReader>) {
+    let (sender, receiver) = std::sync::mpsc::channel();
+    (
+        PipeWriter(sender),
+        io::BufReader::new(PipeReader {
+            receiver,
+            pending: Vec::new(),
+        }),
+    )
+}
+
+struct PipeWriter(std::sync::mpsc::Sender<Vec<u8>>);
+
+impl Write for PipeWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.send(buf.to_vec()).unwrap();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+struct PipeReader {
+    receiver: std::sync::mpsc::Receiver<Vec<u8>>,
+    pending: Vec<u8>,
+}
+
+impl io::Read for PipeReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pending.is_empty() {
+            match self.receiver.recv() {
+                Ok(bytes) => self.pending = bytes,
+                // The writer has been dropped, so the input is closed.
+                Err(_) => return Ok(0),
+            }
+        }
+
+        let count = buf.len().min(self.pending.len());
+        buf[..count].copy_from_slice(&self.pending[..count]);
+        self.pending.drain(..count);
+        Ok(count)
+    }
+}